@@ -0,0 +1,131 @@
+use crate::inspect::human_size;
+use neutauri_data as data;
+
+// 检查打包结果是否超出预算，超出时返回包含所有超限项的报告
+pub(crate) fn check(
+    budgets: &data::Budgets,
+    stats: &data::BuildStats,
+    payload_size: u64,
+) -> anyhow::Result<()> {
+    let mut report = Vec::new();
+    let mut unmatched = Vec::new();
+    if let Some(total) = &budgets.total {
+        let limit = total.bytes()?;
+        if payload_size > limit {
+            report.push(format!(
+                "  {:<24} {:>10} > {}",
+                "total",
+                human_size(payload_size),
+                human_size(limit)
+            ));
+        }
+    }
+    for (pattern, limit) in &budgets.files {
+        let limit = limit.bytes()?;
+        let matcher = data::glob_patterns(std::slice::from_ref(pattern))?;
+        let mut matched = stats
+            .entries
+            .iter()
+            .filter(|e| data::glob_matches_any(&matcher, &e.path))
+            .collect::<Vec<_>>();
+        // 没有匹配任何文件的规则多半是写错了路径，预算形同虚设
+        if matched.is_empty() {
+            unmatched.push(format!("  {}", pattern));
+            continue;
+        }
+        let size: u64 = matched.iter().map(|e| e.compressed_size).sum();
+        if size > limit {
+            report.push(format!(
+                "  {:<24} {:>10} > {}",
+                pattern,
+                human_size(size),
+                human_size(limit)
+            ));
+            matched.sort_by_key(|e| std::cmp::Reverse(e.compressed_size));
+            for entry in matched {
+                report.push(format!(
+                    "    {:<22} {:>10}",
+                    entry.path,
+                    human_size(entry.compressed_size)
+                ));
+            }
+        }
+    }
+    if !unmatched.is_empty() {
+        anyhow::bail!(
+            "Bundle size budget patterns match no files:\n{}",
+            unmatched.join("\n")
+        );
+    }
+    if report.is_empty() {
+        return Ok(());
+    }
+    anyhow::bail!(
+        "Bundle size budget exceeded (compressed sizes):\n{}",
+        report.join("\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn stats() -> data::BuildStats {
+        let entry = |path: &str, compressed_size: u64| data::Entry {
+            path: path.to_string(),
+            mime: "text/javascript".to_string(),
+            compress: data::Compress::Brotli,
+            size: compressed_size * 2,
+            compressed_size,
+        };
+        data::BuildStats {
+            entries: vec![entry("/build/a.js", 600), entry("/build/b.js", 500)],
+            ..Default::default()
+        }
+    }
+
+    fn budgets(total: Option<&str>, files: &[(&str, &str)]) -> data::Budgets {
+        data::Budgets {
+            total: total.map(|total| data::ByteSize::Text(total.to_string())),
+            files: files
+                .iter()
+                .map(|(pattern, limit)| {
+                    (pattern.to_string(), data::ByteSize::Text(limit.to_string()))
+                })
+                .collect::<BTreeMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn within_budget() {
+        let budgets = budgets(Some("2 KiB"), &[("/build/*.js", "1100")]);
+        assert!(check(&budgets, &stats(), 2048).is_ok());
+        assert!(check(&data::Budgets::default(), &stats(), u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn over_budget() {
+        let error = check(&budgets(Some("1 KiB"), &[]), &stats(), 1025).unwrap_err();
+        assert!(error.to_string().contains("total"));
+        // 报告列出匹配的文件，按大小降序
+        let error = check(&budgets(None, &[("build/*.js", "1000")]), &stats(), 0).unwrap_err();
+        let report = error.to_string();
+        let (a, b) = (
+            report.find("/build/a.js").unwrap(),
+            report.find("/build/b.js").unwrap(),
+        );
+        assert!(a < b);
+        assert!(check(&budgets(Some("ten"), &[]), &stats(), 0).is_err());
+    }
+
+    #[test]
+    fn unmatched_patterns() {
+        let budgets = budgets(None, &[("/build/*.js", "2000"), ("/dist/*.js", "1000")]);
+        let error = check(&budgets, &stats(), 0).unwrap_err();
+        let report = error.to_string();
+        assert!(report.contains("match no files"), "{}", report);
+        assert!(report.contains("/dist/*.js"));
+        assert!(!report.contains("/build/*.js"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::budget;
 use anyhow::Context;
 use neutauri_data as data;
 #[cfg(windows)]
@@ -57,7 +58,7 @@ fn get_runtime_data(
     Ok(runtime_data)
 }
 
-pub(crate) fn bundle(config_path: String) -> anyhow::Result<()> {
+pub(crate) fn bundle(config_path: String, source: Option<String>) -> anyhow::Result<()> {
     let config_path = std::path::Path::new(&config_path)
         .canonicalize()
         .with_context(|| {
@@ -66,11 +67,20 @@ pub(crate) fn bundle(config_path: String) -> anyhow::Result<()> {
                 &config_path, "You may want to create a neutauri.toml via the init subcommand?"
             )
         })?;
-    let config: data::Config = toml::from_str(fs::read_to_string(&config_path)?.as_str())
+    let mut config: data::Config = toml::from_str(fs::read_to_string(&config_path)?.as_str())
         .with_context(|| "toml parsing error")?;
-    let source = match config_path.parent() {
-        Some(path) => path.join(&config.source).canonicalize()?,
-        None => config.source.canonicalize()?,
+    config.resolve_mounts(
+        config_path
+            .parent()
+            .unwrap_or_else(|| std::path::Path::new(".")),
+    )?;
+    // 命令行指定的 source 相对于当前目录
+    let source = match (source, config_path.parent()) {
+        (Some(source), _) => std::path::Path::new(&source)
+            .canonicalize()
+            .with_context(|| format!("Error reading source from {}", source))?,
+        (None, Some(path)) => path.join(&config.source).canonicalize()?,
+        (None, None) => config.source.canonicalize()?,
     };
     let target = match config_path.parent() {
         Some(path) => data::normalize_path(&path.join(&config.target)),
@@ -82,14 +92,59 @@ pub(crate) fn bundle(config_path: String) -> anyhow::Result<()> {
     } else {
         target
     };
-    if target.extension() == Some(std::ffi::OsStr::new("neu")) {
-        return data::pack(config_path);
+    let embedded = target.extension() != Some(std::ffi::OsStr::new("neu"));
+    // 自带的运行时没有 on_window_event 回调，隐藏后无法再显示窗口，会拒绝启动
+    if embedded && config.close_behavior == data::CloseBehavior::Hide {
+        anyhow::bail!(
+            "close_behavior = \"hide\" is not supported by the bundled runtime, the window could never be shown again. \
+             Use \"exit\" or \"ask_js\", or bundle to a .neu file and load it from your own runtime with an on_window_event hook"
+        );
+    }
+    let start = std::time::Instant::now();
+    let (data, stats) = data::Data::build_from_dir(source, &config)?;
+    print_stats(&stats, start.elapsed());
+    check_references(config.check_references, &stats)?;
+    budget::check(&config.budgets, &stats, data.len() as u64)?;
+    if !embedded {
+        fs::write(&target, data)?;
+        return Ok(());
     }
-    let data = data::Data::build_from_dir(source, config.window_attr()?, config.webview_attr()?)?;
+    let runtime = get_runtime_data(config.icon, config.manifest)?;
     let mut f = options().open(&target)?;
-    f.write_all(&get_runtime_data(config.icon, config.manifest)?)?;
-    f.write_all(&data)?;
+    f.write_all(
+        &data::embed(&runtime, &data, config.embed)
+            .with_context(|| format!("Failed to embed data into {}", target.display()))?,
+    )?;
     f.sync_all()?;
     f.flush()?;
     Ok(())
 }
+
+// 按配置输出或拒绝会在运行时 404 的引用
+fn check_references(level: data::CheckReferences, stats: &data::BuildStats) -> anyhow::Result<()> {
+    if stats.broken_references.is_empty() {
+        return Ok(());
+    }
+    let report = stats
+        .broken_references
+        .iter()
+        .map(|line| format!("  {}", line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    match level {
+        data::CheckReferences::Error => anyhow::bail!("Broken references found:\n{}", report),
+        _ => eprintln!("Warning: broken references found:\n{}", report),
+    }
+    Ok(())
+}
+
+fn print_stats(stats: &data::BuildStats, elapsed: std::time::Duration) {
+    eprintln!(
+        "Compressed {} files: {} -> {} bytes ({:.1}%) in {:.2?}",
+        stats.files,
+        stats.original_size,
+        stats.compressed_size,
+        stats.ratio() * 100.0,
+        elapsed
+    );
+}
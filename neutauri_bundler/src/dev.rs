@@ -1,22 +1,10 @@
 use anyhow::{Context, Result};
 use neutauri_data as data;
-use std::{fs, io::Read, path::PathBuf, borrow::Cow};
-use wry::{
-    application::{
-        dpi::{PhysicalSize, Size},
-        event::{Event, WindowEvent},
-        event_loop::{ControlFlow, EventLoop},
-        window::{Fullscreen, Icon, Window, WindowBuilder},
-    },
-    webview::{WebContext, WebViewBuilder},
+use std::{
+    fs,
+    path::{Path, PathBuf},
 };
-
-const PROTOCOL_PREFIX: &str = "dev://localhost";
-const PROTOCOL: &str = "dev";
-
-fn custom_protocol_uri<T: Into<String>>(path: T) -> String {
-    PROTOCOL_PREFIX.to_owned() + &path.into()
-}
+use wry::http::StatusCode;
 
 pub(crate) fn dev(config_path: String) -> Result<()> {
     let config_path = std::path::Path::new(&config_path)
@@ -27,195 +15,187 @@ pub(crate) fn dev(config_path: String) -> Result<()> {
                 &config_path, "You may want to create a neutauri.toml via the init subcommand?"
             )
         })?;
-    let config: data::Config = toml::from_str(fs::read_to_string(config_path)?.as_str())
+    let mut config: data::Config = toml::from_str(fs::read_to_string(&config_path)?.as_str())
         .with_context(|| "toml parsing error")?;
+    config.resolve_mounts(config_path.parent().unwrap_or_else(|| Path::new(".")))?;
+    // 优先匹配最长的前缀
+    let mut mounts = config.mounts.clone();
+    mounts.sort_by_key(|mount| std::cmp::Reverse(mount.prefix.len()));
     let source = config.source.canonicalize()?;
+    // 开发模式直接读取磁盘上的文件
+    if data::is_archive(&source) || config.mounts.iter().any(|m| data::is_archive(&m.source)) {
+        anyhow::bail!(
+            "The dev subcommand needs directory sources, archives are only supported when bundling"
+        );
+    }
 
-    let event_loop = EventLoop::new();
+    let webview_attr = config.webview_attr()?;
+    // 与打包后的程序使用相同的数据目录，保证 localStorage 等数据一致
+    let identifier = config
+        .target
+        .file_stem()
+        .unwrap_or_else(|| "neutauri_app".as_ref())
+        .to_string_lossy()
+        .to_string();
+    // 挂载点优先，最后是 source 目录
+    let mut dirs = Vec::new();
+    for mount in &mounts {
+        dirs.push(DevDir::new(&mount.source, &mount.prefix()?, &mount.ignore)?);
+    }
+    dirs.push(DevDir::new(&source, "", &config.ignore)?);
+    neutauri_core::App::new(config.window_attr()?, webview_attr, DevSource { dirs })
+        .with_identifier(identifier)
+        .with_devtools(true)
+        .run()?;
+    Ok(())
+}
 
-    let window_builder = WindowBuilder::new()
-        .with_always_on_top(config.window_attr()?.always_on_top)
-        .with_decorations(config.window_attr()?.decorations)
-        .with_resizable(config.window_attr()?.resizable)
-        .with_title(config.window_attr()?.title)
-        .with_maximized(config.window_attr()?.maximized)
-        .with_transparent(config.window_attr()?.transparent)
-        .with_visible(config.window_attr()?.visible);
-    let window_builder = match config.window_attr()?.fullscreen {
-        true => window_builder.with_fullscreen(Some(Fullscreen::Borderless(None))),
-        false => window_builder,
-    };
-    let window_builder = match config.window_attr()?.icon {
-        Some(ref icon) => window_builder.with_window_icon(Some(Icon::from_rgba(
-            icon.rgba.clone(),
-            icon.width,
-            icon.height,
-        )?)),
-        None => window_builder,
-    };
-    let monitor_size = event_loop
-        .primary_monitor()
-        .unwrap_or_else(|| {
-            event_loop
-                .available_monitors()
-                .next()
-                .expect("no monitor found")
-        })
-        .size();
-    let window_builder = match config.window_attr()?.inner_size {
-        Some(size) => window_builder.with_inner_size(get_size(size, monitor_size)),
-        None => window_builder,
-    };
-    let window_builder = match config.window_attr()?.max_inner_size {
-        Some(size) => window_builder.with_max_inner_size(get_size(size, monitor_size)),
-        None => window_builder,
-    };
-    let window_builder = match config.window_attr()?.min_inner_size {
-        Some(size) => window_builder.with_min_inner_size(get_size(size, monitor_size)),
-        None => window_builder,
-    };
-    let window = window_builder.build(&event_loop)?;
+// 挂载到 prefix 下的目录，与打包时一样跳过匹配 ignore 的文件
+struct DevDir {
+    dir: neutauri_core::LocalDir,
+    prefix: String,
+    ignore: Vec<glob::Pattern>,
+}
 
-    let webview_builder = WebViewBuilder::new(window)?;
-    let url = config.webview_attr()?.url;
-    let webview_builder = match url {
-        Some(url) => {
-            if url.starts_with('/') {
-                webview_builder.with_url(&custom_protocol_uri(&url))?
-            } else {
-                webview_builder.with_url(&url)?
-            }
-        }
-        None => webview_builder.with_url(&custom_protocol_uri("/index.html"))?,
-    };
-    let html = config.webview_attr()?.html;
-    let webview_builder = match html {
-        Some(html) => webview_builder.with_html(&html)?,
-        None => webview_builder,
-    };
-    let initialization_script = config.webview_attr()?.initialization_script;
-    let webview_builder = match initialization_script {
-        Some(script) => webview_builder.with_initialization_script(&script),
-        None => webview_builder,
-    };
-    let webview_builder = match config.window_attr()?.visible {
-        true => webview_builder.with_visible(true),
-        false => webview_builder
-            .with_visible(false)
-            .with_initialization_script(
-                r#"window.addEventListener('load', function(event) { window.ipc.postMessage('show_window'); });"#,
-            ),
-    };
-    let path = std::env::current_exe()?;
-    let path = path.file_stem().unwrap_or_else(|| "neutauri_app".as_ref());
-    let mut web_context = if cfg!(target_os = "windows") {
-        let config_path = match std::env::var("APPDATA") {
-            Ok(dir) => PathBuf::from(dir),
-            Err(_) => PathBuf::from("."),
-        }
-        .join(path);
-        WebContext::new(Some(config_path))
-    } else if cfg!(target_os = "linux") {
-        let config_path = match std::env::var("XDG_CONFIG_DIR") {
-            Ok(dir) => PathBuf::from(dir),
-            Err(_) => match std::env::var("HOME") {
-                Ok(dir) => PathBuf::from(dir).join(".config"),
-                Err(_) => PathBuf::from("."),
-            },
-        }
-        .join(path);
-        WebContext::new(Some(config_path))
-    } else if cfg!(target_os = "macos") {
-        let config_path = match std::env::var("HOME") {
-            Ok(dir) => PathBuf::from(dir).join("Library/Application Support/"),
-            Err(_) => PathBuf::from("."),
-        }
-        .join(path);
-        WebContext::new(Some(config_path))
-    } else {
-        WebContext::new(None)
-    };
-    let webview = webview_builder
-        .with_clipboard(true)
-        .with_visible(config.window_attr()?.visible)
-        .with_transparent(config.window_attr()?.transparent)
-        .with_web_context(&mut web_context)
-        .with_custom_protocol(PROTOCOL.to_string(), move |request| {
-            let path = request.uri().path();
-            let mut local_path = source.clone();
-            local_path.push(path.strip_prefix('/').unwrap_or(path));
-            let mut data = Vec::new();
-            let mut mime: String = "application/octet-stream".to_string();
-            match fs::File::open(&local_path) {
-                Ok(mut f) => {
-                    mime = new_mime_guess::from_path(&local_path)
-                        .first_or_octet_stream()
-                        .to_string();
-                    f.read_to_end(&mut data)?;
-                }
-                Err(e) => {
-                    if e.kind() == std::io::ErrorKind::NotFound && config.webview_attr()?.spa {
-                        let mut index_path = source.clone();
-                        index_path.push("index.html");
-                        mime = new_mime_guess::from_path(&index_path)
-                            .first_or_octet_stream()
-                            .to_string();
-                        let mut f = fs::File::open(index_path)?;
-                        f.read_to_end(&mut data)?;
-                    }
-                }
-            }
-            wry::http::Response::builder()
-                .header("Content-Type", mime)
-                .header("Access-Control-Allow-Origin", "*")
-                .body(Cow::Owned(data))
-                .map_err(|e| e.into())
+impl DevDir {
+    fn new(source: &Path, prefix: &str, ignore: &[String]) -> Result<Self> {
+        Ok(Self {
+            dir: neutauri_core::LocalDir::new(source).with_prefix(prefix),
+            prefix: prefix.to_string(),
+            ignore: data::glob_patterns(ignore)?,
         })
-        .with_ipc_handler(|window: &Window, req: String| {
-            match req.as_str() {
-                "show_window" => window.set_visible(true),
-                "ping" => println!("recived a ping"),
-                _ => (),
-            };
-        })
-        .with_devtools(true)
-        .build()?;
+    }
+
+    fn ignored(&self, path: &str) -> bool {
+        let relative_path = path.strip_prefix(&self.prefix).unwrap_or(path);
+        data::is_ignored(&self.ignore, relative_path)
+    }
+}
 
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Wait;
+// 开发模式直接读取磁盘上的文件，每个请求只由前缀最长的目录提供
+struct DevSource {
+    dirs: Vec<DevDir>,
+}
+
+impl DevSource {
+    fn local_path(&self, path: &str) -> Option<PathBuf> {
+        self.dirs.iter().find_map(|dir| dir.dir.local_path(path))
+    }
+}
 
-        match event {
-            Event::WindowEvent {
-                event: WindowEvent::CloseRequested,
-                ..
-            } => *control_flow = ControlFlow::Exit,
-            Event::GlobalShortcutEvent(id) => webview
-                .evaluate_script(&format!("GlobalShortcutEvent({:})", id.0))
-                .unwrap_or_default(),
-            _ => (),
+impl neutauri_core::AssetSource for DevSource {
+    fn open(&self, path: &str) -> std::io::Result<neutauri_core::Asset> {
+        match self
+            .dirs
+            .iter()
+            .find(|dir| dir.dir.local_path(path).is_some())
+        {
+            Some(dir) if !dir.ignored(path) => dir.dir.open(path),
+            Some(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "file is ignored",
+            )),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "file not found",
+            )),
         }
-    });
+    }
+
+    fn error_page(
+        &self,
+        _webview_attr: &data::WebViewAttr,
+        path: &str,
+        error: &std::io::Error,
+    ) -> Option<neutauri_core::Asset> {
+        let local_path = self.local_path(path).unwrap_or_default();
+        Some(error_page(path, &local_path, error))
+    }
 }
 
-fn get_size(size: data::WindowSize, monitor_size: PhysicalSize<u32>) -> Size {
-    let (width, height) = match size {
-        data::WindowSize::Large => (
-            monitor_size.width as f64 * 0.7,
-            monitor_size.height as f64 * 0.7,
-        ),
-        data::WindowSize::Medium => (
-            monitor_size.width as f64 * 0.6,
-            monitor_size.height as f64 * 0.6,
-        ),
-        data::WindowSize::Small => (
-            monitor_size.width as f64 * 0.5,
-            monitor_size.height as f64 * 0.5,
-        ),
-        data::WindowSize::Fixed { width, height } => (width, height),
-        data::WindowSize::Scale { factor } => (
-            monitor_size.width as f64 * factor,
-            monitor_size.height as f64 * factor,
-        ),
+// 开发模式的错误页面，显示请求路径、对应的本地文件和错误原因
+fn error_page(path: &str, local_path: &Path, error: &std::io::Error) -> neutauri_core::Asset {
+    let status = match error.kind() {
+        std::io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        std::io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
     };
-    Size::Physical(PhysicalSize::new(width as u32, height as u32))
+    eprintln!(
+        "{} {} ({}): {}",
+        status.as_u16(),
+        path,
+        local_path.display(),
+        error
+    );
+    let escape = |text: &str| {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    };
+    let title = format!(
+        "{} {}",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("Error")
+    );
+    let body = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>{title}</title></head>
+<body style="font-family: sans-serif; margin: 2em;">
+<h1>{title}</h1>
+<p>Request: <code>{path}</code></p>
+<p>File: <code>{file}</code></p>
+<p>Reason: <code>{reason}</code></p>
+</body>
+</html>
+"#,
+        title = title,
+        path = escape(path),
+        file = escape(&local_path.display().to_string()),
+        reason = escape(&error.to_string()),
+    );
+    neutauri_core::Asset {
+        data: body.into_bytes(),
+        mime: "text/html; charset=utf-8".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neutauri_core::AssetSource;
+
+    #[test]
+    fn ignored_files() {
+        let root = std::env::temp_dir().join(format!("neutauri_dev_{}", std::process::id()));
+        let docs = root.join("docs");
+        fs::create_dir_all(root.join("src/drafts")).unwrap();
+        fs::create_dir_all(&docs).unwrap();
+        fs::write(root.join("src/index.html"), "index").unwrap();
+        fs::write(root.join("src/app.js.map"), "map").unwrap();
+        fs::write(root.join("src/drafts/a.html"), "draft").unwrap();
+        fs::write(docs.join("guide.html"), "guide").unwrap();
+        fs::write(docs.join("notes.txt"), "notes").unwrap();
+        let source = DevSource {
+            dirs: vec![
+                DevDir::new(&docs, "/docs", &["*.txt".into()]).unwrap(),
+                DevDir::new(&root.join("src"), "", &["**/*.map".into(), "drafts".into()]).unwrap(),
+            ],
+        };
+        let open = |path: &str| source.open(path).map(|asset| asset.data);
+        let result = (
+            open("/index.html").ok(),
+            open("/docs/guide.html").ok(),
+            open("/app.js.map").map_err(|e| e.kind()),
+            open("/drafts/a.html").map_err(|e| e.kind()),
+            open("/docs/notes.txt").map_err(|e| e.kind()),
+        );
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(result.0.as_deref(), Some(&b"index"[..]));
+        assert_eq!(result.1.as_deref(), Some(&b"guide"[..]));
+        assert_eq!(result.2, Err(std::io::ErrorKind::NotFound));
+        assert_eq!(result.3, Err(std::io::ErrorKind::NotFound));
+        assert_eq!(result.4, Err(std::io::ErrorKind::NotFound));
+    }
 }
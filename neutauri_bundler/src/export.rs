@@ -0,0 +1,12 @@
+use anyhow::Context;
+use neutauri_data as data;
+
+pub(crate) fn export(path: String, output: String) -> anyhow::Result<()> {
+    let res = data::Data::new(path.as_str())
+        .with_context(|| format!("Failed to read bundle data from {}", path))?;
+    let files = res
+        .export(&output)
+        .with_context(|| format!("Failed to export files to {}", output))?;
+    eprintln!("Exported {} files to {}", files, output);
+    Ok(())
+}
@@ -0,0 +1,232 @@
+use anyhow::Context;
+use neutauri_data as data;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+#[derive(Serialize, Debug, Default)]
+struct Node {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mime: Option<String>,
+    size: u64,
+    compressed_size: u64,
+    ratio: f64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn insert(&mut self, components: &[&str], entry: &data::Entry) {
+        self.size += entry.size;
+        self.compressed_size += entry.compressed_size;
+        match components {
+            [] => (),
+            [name] => self.children.push(Node {
+                name: name.to_string(),
+                mime: Some(entry.mime.clone()),
+                size: entry.size,
+                compressed_size: entry.compressed_size,
+                ..Default::default()
+            }),
+            [name, rest @ ..] => {
+                let index = match self.children.iter().position(|n| n.name == *name) {
+                    Some(index) => index,
+                    None => {
+                        self.children.push(Node {
+                            name: name.to_string(),
+                            ..Default::default()
+                        });
+                        self.children.len() - 1
+                    }
+                };
+                self.children[index].insert(rest, entry);
+            }
+        }
+    }
+
+    // 计算压缩率并按压缩后大小降序排列
+    fn finish(&mut self) {
+        self.ratio = ratio(self.compressed_size, self.size);
+        self.children
+            .sort_by_key(|n| std::cmp::Reverse(n.compressed_size));
+        self.children.iter_mut().for_each(Node::finish);
+    }
+
+    fn print(&self, prefix: &str, last: bool, root: bool) {
+        let (branch, next) = match (root, last) {
+            (true, _) => ("", ""),
+            (false, true) => ("└── ", "    "),
+            (false, false) => ("├── ", "│   "),
+        };
+        let name = if self.mime.is_none() && !root {
+            format!("{}/", self.name)
+        } else {
+            self.name.clone()
+        };
+        println!(
+            "{:>10} {:>10} {:>6.1}%  {}{}{}{}",
+            human_size(self.compressed_size),
+            human_size(self.size),
+            self.ratio * 100.0,
+            prefix,
+            branch,
+            name,
+            match &self.mime {
+                Some(mime) => format!(" ({})", mime),
+                None => "".to_string(),
+            }
+        );
+        let prefix = format!("{}{}", prefix, next);
+        for (i, child) in self.children.iter().enumerate() {
+            child.print(&prefix, i == self.children.len() - 1, false);
+        }
+    }
+}
+
+fn ratio(compressed_size: u64, size: u64) -> f64 {
+    if size == 0 {
+        1.0
+    } else {
+        compressed_size as f64 / size as f64
+    }
+}
+
+pub(crate) fn human_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = size as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", size, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+// 图标的像素数据没有阅读价值，只保留尺寸
+fn attr_value<T: Serialize>(attr: &T) -> anyhow::Result<Value> {
+    let mut value = serde_json::to_value(attr)?;
+    if let Some(icon) = value.get_mut("icon") {
+        if let Some(object) = icon.as_object_mut() {
+            object.remove("rgba");
+        }
+    }
+    Ok(value)
+}
+
+fn print_attr(title: &str, value: &Value) {
+    println!("{}:", title);
+    if let Some(object) = value.as_object() {
+        for (key, value) in object {
+            println!("  {} = {}", key, value);
+        }
+    }
+}
+
+pub(crate) fn inspect(path: String, json: bool) -> anyhow::Result<()> {
+    let (res, payload) = data::Data::inspect(path.as_str())
+        .with_context(|| format!("Failed to read bundle data from {}", path))?;
+    let mut root = Node::default();
+    let entries = res.entries()?;
+    for entry in &entries {
+        let components = entry
+            .path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        root.insert(&components, entry);
+    }
+    root.name = "/".to_string();
+    root.finish();
+    let window_attr = attr_value(&res.window_attr)?;
+    let webview_attr = attr_value(&res.webview_attr)?;
+    // Section 模式下数据之后还有节名字符串表与节头表，只有数据之前的部分属于运行时
+    let runtime_size = payload.offset;
+
+    if json {
+        let output = json!({
+            "version": payload.version,
+            "embed": payload.embed,
+            "file_size": payload.file_size,
+            "runtime_size": runtime_size,
+            "payload_size": payload.size,
+            "window_attr": window_attr,
+            "webview_attr": webview_attr,
+            "entries": root,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    println!("Format:  NEUTFS{}", payload.version);
+    println!("Layout:  {:?}", payload.embed);
+    println!("Runtime: {}", human_size(runtime_size));
+    println!(
+        "Payload: {} ({} files, {} uncompressed)",
+        human_size(payload.size),
+        entries.len(),
+        human_size(root.size)
+    );
+    println!();
+    print_attr("Window", &window_attr);
+    println!();
+    print_attr("WebView", &webview_attr);
+    println!();
+    println!("{:>10} {:>10} {:>7}  Path", "Stored", "Original", "Ratio");
+    root.print("", true, true);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, size: u64, compressed_size: u64) -> data::Entry {
+        data::Entry {
+            path: path.to_string(),
+            mime: "text/plain".to_string(),
+            compress: data::Compress::Brotli,
+            size,
+            compressed_size,
+        }
+    }
+
+    #[test]
+    fn size_tree() {
+        let mut root = Node::default();
+        for entry in [
+            entry("/index.html", 100, 50),
+            entry("/build/a.js", 400, 100),
+            entry("/build/b.js", 200, 150),
+        ] {
+            let components = entry
+                .path
+                .split('/')
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>();
+            root.insert(&components, &entry);
+        }
+        root.finish();
+        assert_eq!((root.size, root.compressed_size), (700, 300));
+        // 按压缩后大小降序排列
+        let build = &root.children[0];
+        assert_eq!(build.name, "build");
+        assert!(build.mime.is_none());
+        assert_eq!((build.size, build.compressed_size), (600, 250));
+        assert_eq!(build.children[0].name, "b.js");
+        assert_eq!(build.children[1].ratio, 0.25);
+        assert_eq!(root.children[1].name, "index.html");
+    }
+
+    #[test]
+    fn human_sizes() {
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(3 << 20), "3.0 MiB");
+        assert_eq!(human_size(5 << 40), "5120.0 GiB");
+    }
+}
@@ -1,7 +1,10 @@
 use gumdrop::Options;
+mod budget;
 mod bundle;
 mod dev;
+mod export;
 mod init;
+mod inspect;
 
 #[derive(Debug, Options)]
 struct Args {
@@ -20,8 +23,12 @@ enum Command {
     Bundle(BundleOpts),
     #[options(help = "run the project in the current directory in development mode")]
     Dev(DevOpts),
+    #[options(help = "export the files of a bundle to a zip or tar archive")]
+    Export(ExportOpts),
     #[options(help = "initialize a neutauri project")]
     Init(InitOpts),
+    #[options(help = "show the contents and size breakdown of a bundle")]
+    Inspect(InspectOpts),
 }
 
 #[derive(Debug, Clone, Options)]
@@ -30,6 +37,8 @@ struct BundleOpts {
     help: bool,
     #[options(help = "path to the config file [default: neutauri.toml]")]
     config: Option<String>,
+    #[options(help = "source directory or .zip/.tar.gz archive, overrides the config file")]
+    source: Option<String>,
 }
 
 #[derive(Debug, Clone, Options)]
@@ -40,12 +49,32 @@ struct DevOpts {
     config: Option<String>,
 }
 
+#[derive(Debug, Clone, Options)]
+struct ExportOpts {
+    #[options(help = "print help information")]
+    help: bool,
+    #[options(help = "path to the output archive (.zip, .tar or .tar.gz)")]
+    output: Option<String>,
+    #[options(free, help = "path to the bundled program or .neu file")]
+    path: Option<String>,
+}
+
 #[derive(Debug, Clone, Options)]
 struct InitOpts {
     #[options(help = "print help information")]
     help: bool,
 }
 
+#[derive(Debug, Clone, Options)]
+struct InspectOpts {
+    #[options(help = "print help information")]
+    help: bool,
+    #[options(help = "output in JSON format")]
+    json: bool,
+    #[options(free, help = "path to the bundled program or .neu file")]
+    path: Option<String>,
+}
+
 fn print_help_and_exit(args: Args) {
     if args.command.is_some() {
         Args::parse_args_default_or_exit();
@@ -80,7 +109,7 @@ fn main() -> anyhow::Result<()> {
                     print_help_and_exit(args);
                 }
                 let config_path = opts.config.unwrap_or_else(|| "neutauri.toml".to_string());
-                bundle::bundle(config_path)?;
+                bundle::bundle(config_path, opts.source)?;
             }
             Command::Dev(opts) => {
                 if opts.help_requested() {
@@ -91,6 +120,17 @@ fn main() -> anyhow::Result<()> {
                 let config_path = opts.config.unwrap_or_else(|| "neutauri.toml".to_string());
                 dev::dev(config_path)?;
             }
+            Command::Export(opts) => {
+                if opts.help_requested() || opts.path.is_none() || opts.output.is_none() {
+                    eprintln!("Write the files stored in a bundle to a zip or tar archive");
+                    eprintln!();
+                    print_help_and_exit(args);
+                }
+                export::export(
+                    opts.path.unwrap_or_default(),
+                    opts.output.unwrap_or_default(),
+                )?;
+            }
             Command::Init(opts) => {
                 if opts.help_requested() {
                     eprintln!("Interactively create a neutauri.toml configuration file");
@@ -99,6 +139,14 @@ fn main() -> anyhow::Result<()> {
                 }
                 init::init()?;
             }
+            Command::Inspect(opts) => {
+                if opts.help_requested() || opts.path.is_none() {
+                    eprintln!("Show the configuration and files stored in a bundle");
+                    eprintln!();
+                    print_help_and_exit(args);
+                }
+                inspect::inspect(opts.path.unwrap_or_default(), opts.json)?;
+            }
         },
         None => print_help_and_exit(args),
     }
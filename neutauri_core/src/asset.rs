@@ -0,0 +1,424 @@
+use neutauri_data as data;
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+use wry::http::{Response, StatusCode};
+
+pub struct Asset {
+    pub data: Vec<u8>,
+    pub mime: String,
+}
+
+// 资源后端，路径均以 / 开头，文件不存在时应返回 NotFound 以便回退到其他后端或页面
+pub trait AssetSource: Send + Sync {
+    fn open(&self, path: &str) -> io::Result<Asset>;
+
+    // 读取失败时的响应内容，默认使用配置的 error_page
+    fn error_page(
+        &self,
+        webview_attr: &data::WebViewAttr,
+        _path: &str,
+        _error: &io::Error,
+    ) -> Option<Asset> {
+        let page = webview_attr.error_page.as_ref()?;
+        self.open(&format!("/{}", page.trim_start_matches('/')))
+            .ok()
+    }
+}
+
+impl AssetSource for data::Data {
+    fn open(&self, path: &str) -> io::Result<Asset> {
+        let mut file = data::Data::open(self, path)?;
+        Ok(Asset {
+            data: file.decompressed_data()?,
+            mime: file.mimetype(),
+        })
+    }
+}
+
+impl<S: AssetSource + ?Sized> AssetSource for Box<S> {
+    fn open(&self, path: &str) -> io::Result<Asset> {
+        self.as_ref().open(path)
+    }
+
+    fn error_page(
+        &self,
+        webview_attr: &data::WebViewAttr,
+        path: &str,
+        error: &io::Error,
+    ) -> Option<Asset> {
+        self.as_ref().error_page(webview_attr, path, error)
+    }
+}
+
+// 本地目录，可以挂载到某个前缀下，如 "/docs"
+pub struct LocalDir {
+    root: PathBuf,
+    prefix: String,
+}
+
+impl LocalDir {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            prefix: String::new(),
+        }
+    }
+
+    pub fn with_prefix<S: AsRef<str>>(mut self, prefix: S) -> Self {
+        self.prefix = prefix.as_ref().trim_end_matches('/').to_string();
+        self
+    }
+
+    // 将请求路径映射到本地文件，不属于该前缀或试图跳出目录时返回 None；
+    // 每一段都必须是普通文件名，"..", "C:" 与 Windows 的 \ 分隔符都会被拒绝
+    pub fn local_path(&self, path: &str) -> Option<PathBuf> {
+        let rest = path.strip_prefix(&self.prefix)?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+        let mut local_path = self.root.clone();
+        for part in rest.split('/').filter(|part| !part.is_empty()) {
+            let mut components = Path::new(part).components();
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(name)), None) if !part.contains(['\\', ':']) => {
+                    local_path.push(name)
+                }
+                _ => return None,
+            }
+        }
+        Some(local_path)
+    }
+}
+
+impl AssetSource for LocalDir {
+    fn open(&self, path: &str) -> io::Result<Asset> {
+        let local_path = self
+            .local_path(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found"))?;
+        Ok(Asset {
+            data: fs::read(&local_path)?,
+            mime: mime_from_path(&local_path),
+        })
+    }
+}
+
+// 按顺序查找，前面的后端返回 NotFound 时继续查找下一个
+#[derive(Default)]
+pub struct Layered {
+    layers: Vec<Box<dyn AssetSource>>,
+}
+
+impl Layered {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with<S: AssetSource + 'static>(mut self, source: S) -> Self {
+        self.layers.push(Box::new(source));
+        self
+    }
+}
+
+impl AssetSource for Layered {
+    fn open(&self, path: &str) -> io::Result<Asset> {
+        let mut not_found = None;
+        for layer in &self.layers {
+            match layer.open(path) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    not_found.get_or_insert(e);
+                }
+                result => return result,
+            }
+        }
+        Err(not_found.unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found")))
+    }
+}
+
+fn mime_from_path(path: &Path) -> String {
+    new_mime_guess::from_path(path)
+        .first_or_octet_stream()
+        .to_string()
+}
+
+// 依次处理路由规则、目录索引、单页应用与 not_found 回退，并附加配置的响应头
+pub fn respond(
+    source: &dyn AssetSource,
+    webview_attr: &data::WebViewAttr,
+    path: &str,
+) -> wry::http::Result<Response<Vec<u8>>> {
+    let mut path = data::percent_decode(path);
+    match webview_attr.route(&path) {
+        Some(data::Resolution::Redirect { status, location }) => {
+            return Response::builder()
+                .status(status)
+                .header("Location", location)
+                .body(Vec::new());
+        }
+        Some(data::Resolution::Rewrite(target)) => path = target,
+        None => (),
+    }
+    if path.ends_with('/') {
+        path.push_str("index.html");
+    }
+    let opened = match source.open(&path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => match &webview_attr.not_found {
+            _ if webview_attr.spa && path != "/index.html" => source
+                .open("/index.html")
+                .map(|asset| ("/index.html".to_string(), asset, 200))
+                .map_err(|_| e),
+            Some(page) => {
+                let page = format!("/{}", page.trim_start_matches('/'));
+                source
+                    .open(&page)
+                    .map(|asset| (page, asset, 404))
+                    .map_err(|_| e)
+            }
+            None => Err(e),
+        },
+        opened => opened.map(|asset| (path.clone(), asset, 200)),
+    };
+    let (path, asset, status) = match opened {
+        Ok(opened) => opened,
+        Err(e) => return error_response(source, webview_attr, &path, &e),
+    };
+    let (mime, headers) = webview_attr.headers_for(&path);
+    let mut response = Response::builder()
+        .status(status)
+        .header("Content-Type", mime.unwrap_or(asset.mime));
+    for (name, value) in headers {
+        response = response.header(name, value);
+    }
+    response.body(asset.data)
+}
+
+// 使用后端提供的错误页面，没有时返回纯文本
+fn error_response(
+    source: &dyn AssetSource,
+    webview_attr: &data::WebViewAttr,
+    path: &str,
+    error: &io::Error,
+) -> wry::http::Result<Response<Vec<u8>>> {
+    let status = match error.kind() {
+        io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    let asset = source
+        .error_page(webview_attr, path, error)
+        .unwrap_or_else(|| Asset {
+            data: format!(
+                "{} {}",
+                status.as_u16(),
+                status.canonical_reason().unwrap_or("Error")
+            )
+            .into_bytes(),
+            mime: "text/plain".to_string(),
+        });
+    Response::builder()
+        .status(status)
+        .header("Content-Type", asset.mime)
+        .body(asset.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // 内存中的资源，可以指定读取某个路径时返回的错误
+    #[derive(Default)]
+    struct Memory(HashMap<String, Result<&'static str, io::ErrorKind>>);
+
+    impl Memory {
+        fn with(mut self, path: &str, content: &'static str) -> Self {
+            self.0.insert(path.to_string(), Ok(content));
+            self
+        }
+
+        fn with_error(mut self, path: &str, kind: io::ErrorKind) -> Self {
+            self.0.insert(path.to_string(), Err(kind));
+            self
+        }
+    }
+
+    impl AssetSource for Memory {
+        fn open(&self, path: &str) -> io::Result<Asset> {
+            match self.0.get(path) {
+                Some(Ok(content)) => Ok(Asset {
+                    data: content.as_bytes().to_vec(),
+                    mime: match path.ends_with(".html") {
+                        true => "text/html".to_string(),
+                        false => "text/plain".to_string(),
+                    },
+                }),
+                Some(Err(kind)) => Err(io::Error::new(*kind, "failed")),
+                None => Err(io::Error::new(io::ErrorKind::NotFound, "file not found")),
+            }
+        }
+    }
+
+    fn webview_attr(config: data::Config) -> data::WebViewAttr {
+        config.webview_attr().unwrap()
+    }
+
+    fn get(
+        source: &dyn AssetSource,
+        webview_attr: &data::WebViewAttr,
+        path: &str,
+    ) -> (u16, String, String) {
+        let response = respond(source, webview_attr, path).unwrap();
+        let mime = response.headers()["Content-Type"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = String::from_utf8(response.body().clone()).unwrap();
+        (response.status().as_u16(), mime, body)
+    }
+
+    #[test]
+    fn local_paths() {
+        let root = LocalDir::new("/srv/app");
+        assert_eq!(
+            root.local_path("/a/b.js").unwrap(),
+            Path::new("/srv/app/a/b.js")
+        );
+        assert_eq!(root.local_path("//a//").unwrap(), Path::new("/srv/app/a"));
+        for path in [
+            "/../etc/passwd",
+            "/a/../../b",
+            "/./a",
+            "/..\\a",
+            "/a\\..\\..\\b",
+            "/C:/Windows/win.ini",
+            "/a/C:b",
+            "/a.txt:stream",
+        ] {
+            assert!(root.local_path(path).is_none(), "{}", path);
+        }
+        let docs = LocalDir::new("/srv/docs").with_prefix("/docs/");
+        assert_eq!(
+            docs.local_path("/docs/a.html").unwrap(),
+            Path::new("/srv/docs/a.html")
+        );
+        assert_eq!(docs.local_path("/docs").unwrap(), Path::new("/srv/docs"));
+        assert!(docs.local_path("/docsa/b.html").is_none());
+        assert!(docs.local_path("/a.html").is_none());
+        assert!(docs.local_path("/docs/../a.html").is_none());
+    }
+
+    // 前面的后端缺少文件时继续查找，其他错误直接返回
+    #[test]
+    fn layered_sources() {
+        let root = std::env::temp_dir().join(format!("neutauri_layered_{}", std::process::id()));
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("docs/a.txt"), "local").unwrap();
+        let source = Layered::new()
+            .with(LocalDir::new(&root))
+            .with(
+                Memory::default()
+                    .with("/docs/a.txt", "memory")
+                    .with("/b.txt", "memory")
+                    .with_error("/c.txt", io::ErrorKind::PermissionDenied),
+            )
+            .with(Memory::default().with("/c.txt", "unreachable"));
+        let read = |path: &str| source.open(path).map(|asset| asset.data);
+        let result = (read("/docs/a.txt"), read("/b.txt"), read("/c.txt"));
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(result.0.unwrap(), b"local");
+        assert_eq!(result.1.unwrap(), b"memory");
+        assert_eq!(
+            result.2.unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+        assert_eq!(read("/d.txt").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    // 路由规则、目录索引、单页应用回退与响应头
+    #[test]
+    fn routing() {
+        let source = Memory::default()
+            .with("/index.html", "index")
+            .with("/docs/index.html", "docs")
+            .with("/docs/my file.txt", "file")
+            .with("/new/a.txt", "a");
+        let attr = webview_attr(data::Config {
+            spa: true,
+            routes: vec![
+                data::Route {
+                    from: "/old/*".into(),
+                    to: "/new/*".into(),
+                    status: None,
+                },
+                data::Route {
+                    from: "/moved".into(),
+                    to: "/docs/".into(),
+                    status: Some(301),
+                },
+            ],
+            headers: vec![data::HeaderRule {
+                path: "**/*.txt".into(),
+                mime: Some("text/markdown".into()),
+                headers: [("X-Test".to_string(), "1".to_string())].into(),
+            }],
+            ..Default::default()
+        });
+        assert_eq!(
+            get(&source, &attr, "/old/a.txt"),
+            (200, "text/markdown".into(), "a".into())
+        );
+        let response = respond(&source, &attr, "/old/a.txt").unwrap();
+        assert_eq!(response.headers()["x-test"], "1");
+        let response = respond(&source, &attr, "/moved").unwrap();
+        assert_eq!(response.status(), 301);
+        assert_eq!(response.headers()["location"], "/docs/");
+        assert_eq!(get(&source, &attr, "/docs/").2, "docs");
+        assert_eq!(get(&source, &attr, "/docs/my%20file.txt").2, "file");
+        assert_eq!(
+            get(&source, &attr, "/settings"),
+            (200, "text/html".into(), "index".into())
+        );
+    }
+
+    #[test]
+    fn error_statuses() {
+        let source = Memory::default()
+            .with_error("/secret.txt", io::ErrorKind::PermissionDenied)
+            .with_error("/broken.txt", io::ErrorKind::InvalidData);
+        let attr = webview_attr(data::Config::default());
+        assert_eq!(
+            get(&source, &attr, "/missing.txt"),
+            (404, "text/plain".into(), "404 Not Found".into())
+        );
+        assert_eq!(get(&source, &attr, "/secret.txt").0, 403);
+        assert_eq!(get(&source, &attr, "/broken.txt").0, 500);
+    }
+
+    #[test]
+    fn error_pages() {
+        let source = Memory::default()
+            .with("/404.html", "not found")
+            .with("/error.html", "error")
+            .with_error("/broken.txt", io::ErrorKind::InvalidData);
+        let attr = webview_attr(data::Config {
+            not_found: Some("404.html".into()),
+            error_page: Some("/error.html".into()),
+            ..Default::default()
+        });
+        assert_eq!(
+            get(&source, &attr, "/missing.txt"),
+            (404, "text/html".into(), "not found".into())
+        );
+        assert_eq!(
+            get(&source, &attr, "/broken.txt"),
+            (500, "text/html".into(), "error".into())
+        );
+        // not_found 页面本身缺失时使用 error_page
+        let source = Memory::default().with("/error.html", "error");
+        assert_eq!(
+            get(&source, &attr, "/missing.txt"),
+            (404, "text/html".into(), "error".into())
+        );
+    }
+}
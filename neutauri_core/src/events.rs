@@ -0,0 +1,109 @@
+use serde_json::{json, Value};
+use wry::application::{event::WindowEvent, window::Theme};
+
+// window.neutauri.on(event, callback) 订阅原生事件，返回取消订阅的函数
+pub(crate) const SCRIPT: &str = r#"(function () {
+  var neutauri = window.neutauri = window.neutauri || {};
+  var listeners = {};
+  neutauri.on = function (event, callback) {
+    (listeners[event] = listeners[event] || []).push(callback);
+    return function () {
+      var list = listeners[event] || [];
+      var index = list.indexOf(callback);
+      if (index >= 0) list.splice(index, 1);
+    };
+  };
+  neutauri.__emit = function (event, payload) {
+    (listeners[event] || []).slice().forEach(function (callback) {
+      try { callback(payload); } catch (e) { console.error(e); }
+    });
+  };
+})();"#;
+
+// 触发页面中 event 的所有监听函数
+pub(crate) fn emit(event: &str, payload: Value) -> String {
+    format!(
+        "window.neutauri.__emit({}, {});",
+        Value::from(event),
+        payload
+    )
+}
+
+// 窗口事件对应的页面事件：focus、blur、resize、move、theme 与 scale-factor，
+// close-requested 由 window::Close 按 close_behavior 触发
+pub(crate) fn window_event(event: &WindowEvent) -> Option<String> {
+    let (name, payload) = match event {
+        WindowEvent::Focused(true) => ("focus", Value::Null),
+        WindowEvent::Focused(false) => ("blur", Value::Null),
+        WindowEvent::Resized(size) => (
+            "resize",
+            json!({ "width": size.width, "height": size.height }),
+        ),
+        WindowEvent::Moved(position) => ("move", json!({ "x": position.x, "y": position.y })),
+        WindowEvent::ThemeChanged(theme) => {
+            let theme = match theme {
+                Theme::Dark => "dark",
+                _ => "light",
+            };
+            ("theme", json!({ "theme": theme }))
+        }
+        WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+            ("scale-factor", json!({ "scaleFactor": scale_factor }))
+        }
+        _ => return None,
+    };
+    Some(emit(name, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wry::application::dpi::{PhysicalPosition, PhysicalSize};
+
+    #[test]
+    fn emit_scripts() {
+        assert_eq!(
+            emit("shortcut", json!({ "id": 1 })),
+            r#"window.neutauri.__emit("shortcut", {"id":1});"#
+        );
+        assert_eq!(
+            emit("a\"b", Value::Null),
+            r#"window.neutauri.__emit("a\"b", null);"#
+        );
+    }
+
+    #[test]
+    fn window_events() {
+        let script = |event| window_event(&event).unwrap();
+        assert_eq!(
+            script(WindowEvent::Focused(true)),
+            emit("focus", Value::Null)
+        );
+        assert_eq!(
+            script(WindowEvent::Focused(false)),
+            emit("blur", Value::Null)
+        );
+        assert_eq!(
+            script(WindowEvent::Resized(PhysicalSize::new(800, 600))),
+            emit("resize", json!({ "width": 800, "height": 600 }))
+        );
+        assert_eq!(
+            script(WindowEvent::Moved(PhysicalPosition::new(-10, 20))),
+            emit("move", json!({ "x": -10, "y": 20 }))
+        );
+        assert_eq!(
+            script(WindowEvent::ThemeChanged(Theme::Dark)),
+            emit("theme", json!({ "theme": "dark" }))
+        );
+        let mut size = PhysicalSize::new(800, 600);
+        assert_eq!(
+            script(WindowEvent::ScaleFactorChanged {
+                scale_factor: 2.0,
+                new_inner_size: &mut size,
+            }),
+            emit("scale-factor", json!({ "scaleFactor": 2.0 }))
+        );
+        // close-requested 由 window::Close 处理
+        assert!(window_event(&WindowEvent::CloseRequested).is_none());
+    }
+}
@@ -0,0 +1,70 @@
+use crate::ipc::Commands;
+use wry::{
+    application::{event::WindowEvent, event_loop::ControlFlow, window::Window},
+    webview::WebView,
+};
+
+type ReadyHook = Box<dyn FnOnce(&WebView)>;
+type IpcHook = Box<dyn Fn(&Window, &str) -> bool>;
+type WindowEventHook = Box<dyn FnMut(&WindowEvent, &WebView, &mut ControlFlow)>;
+
+// 嵌入方注册的回调，均在主线程中调用
+#[derive(Default)]
+pub struct Hooks {
+    pub(crate) ready: Vec<ReadyHook>,
+    pub(crate) ipc: Vec<IpcHook>,
+    pub(crate) window_event: Vec<WindowEventHook>,
+    pub(crate) commands: Commands,
+}
+
+impl Hooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // webview 创建完成、进入事件循环之前调用
+    pub fn on_ready<F: FnOnce(&WebView) + 'static>(mut self, hook: F) -> Self {
+        self.ready.push(Box::new(hook));
+        self
+    }
+
+    // 注册可以通过 window.neutauri.invoke(name, args) 调用的命令，在固定数量的工作线程中执行，panic 时调用以错误结束，
+    // 以 "window." 开头的名称保留给内置的窗口操作
+    pub fn command<A, R, E, F>(mut self, name: &str, handler: F) -> Self
+    where
+        A: serde::de::DeserializeOwned,
+        R: serde::Serialize,
+        E: std::fmt::Display,
+        F: Fn(A) -> Result<R, E> + Send + Sync + 'static,
+    {
+        self.commands.register(name, handler);
+        self
+    }
+
+    // 注册可以通过 window.neutauri.invokeBinary(name, body) 调用的命令，用于传递文件、图片等二进制数据；
+    // protocol 模式下自定义协议的回调是同步的，命令在主线程中执行，期间窗口无法响应，
+    // 只适合快速完成的操作，耗时的处理应放到 command 中或使用 localhost 模式
+    pub fn binary_command<E, F>(mut self, name: &str, handler: F) -> Self
+    where
+        E: std::fmt::Display,
+        F: Fn(Vec<u8>) -> Result<Vec<u8>, E> + Send + Sync + 'static,
+    {
+        self.commands.register_binary(name, handler);
+        self
+    }
+
+    // 处理页面通过 window.ipc.postMessage 发送的消息，返回 true 表示已处理，不再交给后面的回调
+    pub fn on_ipc<F: Fn(&Window, &str) -> bool + 'static>(mut self, hook: F) -> Self {
+        self.ipc.push(Box::new(hook));
+        self
+    }
+
+    // 在默认处理之后调用，可以修改 control_flow，如收到 CloseRequested 时改回 Wait 取消关闭（hide 除外）
+    pub fn on_window_event<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(&WindowEvent, &WebView, &mut ControlFlow) + 'static,
+    {
+        self.window_event.push(Box::new(hook));
+        self
+    }
+}
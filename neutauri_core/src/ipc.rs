@@ -0,0 +1,350 @@
+use crate::window;
+use neutauri_data as data;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex},
+};
+use wry::http::{Method, Request, Response, StatusCode};
+
+// POST 到该前缀的请求按命令调用，请求体与响应体可以是任意二进制数据
+pub(crate) const INVOKE_PREFIX: &str = "/__neutauri/invoke/";
+// 跨域请求携带自定义请求头时需要预检，可以阻止其他来源的页面调用命令
+const INVOKE_HEADER: &str = "X-Neutauri-Invoke";
+// 执行命令的线程数，同时进行的调用超出时排队等待
+const WORKERS: usize = 4;
+
+// window.neutauri.invoke(cmd, args) 返回 Promise，结果由 Rust 通过 __resolve 交还
+pub(crate) const SCRIPT: &str = r#"(function () {
+  var neutauri = window.neutauri = window.neutauri || {};
+  var pending = {};
+  var next = 0;
+  neutauri.invoke = function (cmd, args) {
+    return new Promise(function (resolve, reject) {
+      var id = ++next;
+      pending[id] = { resolve: resolve, reject: reject };
+      window.ipc.postMessage(JSON.stringify({ id: id, cmd: cmd, args: args === undefined ? null : args }));
+    });
+  };
+  neutauri.__resolve = function (id, ok, value) {
+    var request = pending[id];
+    if (!request) return;
+    delete pending[id];
+    if (ok) request.resolve(value);
+    else request.reject(new Error(value));
+  };
+  neutauri.invokeBinary = function (cmd, body) {
+    return fetch('/__neutauri/invoke/' + encodeURIComponent(cmd), { method: 'POST', headers: { 'X-Neutauri-Invoke': '1' }, body: body })
+      .then(function (response) {
+        if (response.ok) return response.arrayBuffer();
+        return response.text().then(function (text) { throw new Error(text); });
+      });
+  };
+})();"#;
+
+type JsonHandler = Box<dyn Fn(Value) -> Result<Value, String> + Send + Sync>;
+type BinaryHandler = Box<dyn Fn(Vec<u8>) -> Result<Vec<u8>, String> + Send + Sync>;
+
+enum Handler {
+    Json(JsonHandler),
+    Binary(BinaryHandler),
+}
+
+// 原生命令表，JSON 命令的参数与返回值通过 serde 转换，二进制命令原样传递
+#[derive(Default)]
+pub struct Commands {
+    handlers: HashMap<String, Handler>,
+}
+
+impl Commands {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 命令名不能以保留的 window. 开头，否则 panic
+    pub fn register<A, R, E, F>(&mut self, name: &str, handler: F)
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        E: std::fmt::Display,
+        F: Fn(A) -> Result<R, E> + Send + Sync + 'static,
+    {
+        check_name(name);
+        let command = name.to_string();
+        self.handlers.insert(
+            name.to_string(),
+            Handler::Json(Box::new(move |args| {
+                let args = serde_json::from_value(args)
+                    .map_err(|e| format!("invalid arguments for {}: {}", command, e))?;
+                let result = handler(args).map_err(|e| e.to_string())?;
+                serde_json::to_value(result).map_err(|e| e.to_string())
+            })),
+        );
+    }
+
+    // 只能通过 window.neutauri.invokeBinary 调用，请求体与返回值原样传递，
+    // 在处理资源请求的线程中执行（protocol 模式下为主线程）
+    pub fn register_binary<E, F>(&mut self, name: &str, handler: F)
+    where
+        E: std::fmt::Display,
+        F: Fn(Vec<u8>) -> Result<Vec<u8>, E> + Send + Sync + 'static,
+    {
+        check_name(name);
+        self.handlers.insert(
+            name.to_string(),
+            Handler::Binary(Box::new(move |body| {
+                handler(body).map_err(|e| e.to_string())
+            })),
+        );
+    }
+
+    pub fn invoke(&self, name: &str, args: Value) -> Result<Value, String> {
+        match self.handlers.get(name) {
+            Some(Handler::Json(handler)) => call(name, || handler(args)),
+            Some(Handler::Binary(_)) => Err(format!(
+                "{} takes a binary payload, call it with window.neutauri.invokeBinary",
+                name
+            )),
+            None => Err(format!("unknown command {}", name)),
+        }
+    }
+
+    // 处理 POST /__neutauri/invoke/<cmd>，JSON 命令的请求体为参数，空请求体视为 null
+    pub(crate) fn respond(
+        &self,
+        request: &Request<Vec<u8>>,
+        name: &str,
+    ) -> wry::http::Result<Response<Vec<u8>>> {
+        if request.method() != Method::POST {
+            return error(StatusCode::METHOD_NOT_ALLOWED, "expected a POST request");
+        }
+        if !request.headers().contains_key(INVOKE_HEADER) {
+            return error(StatusCode::FORBIDDEN, "missing X-Neutauri-Invoke header");
+        }
+        // 页面中的命令名经过 encodeURIComponent 编码
+        let name = data::percent_decode(name);
+        let name = name.as_str();
+        let (body, mime) = match self.handlers.get(name) {
+            Some(Handler::Binary(handler)) => {
+                match call(name, || handler(request.body().clone())) {
+                    Ok(body) => (body, "application/octet-stream"),
+                    Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, &e),
+                }
+            }
+            Some(Handler::Json(handler)) => {
+                let args = match request.body().is_empty() {
+                    true => Ok(Value::Null),
+                    false => serde_json::from_slice(request.body()),
+                };
+                let args = match args {
+                    Ok(args) => args,
+                    Err(e) => return error(StatusCode::BAD_REQUEST, &e.to_string()),
+                };
+                match call(name, || handler(args)) {
+                    Ok(value) => (value.to_string().into_bytes(), "application/json"),
+                    Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, &e),
+                }
+            }
+            None => return error(StatusCode::NOT_FOUND, &format!("unknown command {}", name)),
+        };
+        Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", mime)
+            .body(body)
+    }
+}
+
+// 命令 panic 时返回错误，页面中的 Promise 不会一直等待
+fn call<T, F: FnOnce() -> Result<T, String>>(name: &str, handler: F) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(handler))
+        .unwrap_or_else(|_| Err(format!("command {} panicked", name)))
+}
+
+fn error(status: StatusCode, message: &str) -> wry::http::Result<Response<Vec<u8>>> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "text/plain; charset=utf-8")
+        .body(message.as_bytes().to_vec())
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// 固定数量的工作线程，命令可能耗时较长，不在主线程中执行
+pub(crate) struct Pool {
+    sender: mpsc::Sender<Job>,
+}
+
+impl Pool {
+    pub(crate) fn new() -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..WORKERS {
+            let receiver = receiver.clone();
+            std::thread::spawn(move || loop {
+                let job = match receiver.lock() {
+                    Ok(receiver) => receiver.recv(),
+                    Err(_) => break,
+                };
+                match job {
+                    Ok(job) => job(),
+                    Err(_) => break,
+                }
+            });
+        }
+        Self { sender }
+    }
+
+    pub(crate) fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        self.sender.send(Box::new(job)).unwrap_or_default();
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Invoke {
+    pub(crate) id: u64,
+    pub(crate) cmd: String,
+    #[serde(default)]
+    pub(crate) args: Value,
+}
+
+// window. 开头的命令名保留给内置的窗口操作，注册了也无法被调用
+fn check_name(name: &str) {
+    assert!(
+        !name.starts_with(window::PREFIX),
+        "command name {} uses the reserved prefix {}",
+        name,
+        window::PREFIX
+    );
+}
+
+// 把调用结果交还给页面的脚本
+pub(crate) fn reply(id: u64, result: Result<Value, String>) -> String {
+    let (ok, value) = match result {
+        Ok(value) => (true, value),
+        Err(e) => (false, Value::String(e)),
+    };
+    format!("window.neutauri.__resolve({}, {}, {});", id, ok, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn commands() -> Commands {
+        let mut commands = Commands::new();
+        commands.register("add", |(a, b): (i64, i64)| Ok::<_, String>(a + b));
+        commands.register("fail", |_: Value| Err::<Value, _>("failed"));
+        commands.register("panic", |_: Value| -> Result<Value, String> {
+            panic!("boom")
+        });
+        commands.register_binary("reverse", |mut body: Vec<u8>| {
+            body.reverse();
+            Ok::<_, String>(body)
+        });
+        commands.register_binary("file/read all", |body: Vec<u8>| Ok::<_, String>(body));
+        commands
+    }
+
+    fn post(commands: &Commands, name: &str, body: &[u8]) -> (u16, String) {
+        let request = Request::builder()
+            .method(Method::POST)
+            .header(INVOKE_HEADER, "1")
+            .body(body.to_vec())
+            .unwrap();
+        let response = commands.respond(&request, name).unwrap();
+        (
+            response.status().as_u16(),
+            String::from_utf8_lossy(response.body()).into_owned(),
+        )
+    }
+
+    #[test]
+    fn invoke() {
+        let commands = commands();
+        assert_eq!(commands.invoke("add", json!([1, 2])).unwrap(), json!(3));
+        assert!(commands
+            .invoke("add", json!("x"))
+            .unwrap_err()
+            .starts_with("invalid arguments for add"));
+        assert_eq!(commands.invoke("fail", Value::Null).unwrap_err(), "failed");
+        assert_eq!(
+            commands.invoke("panic", Value::Null).unwrap_err(),
+            "command panic panicked"
+        );
+        assert!(commands.invoke("reverse", Value::Null).is_err());
+        assert_eq!(
+            commands.invoke("missing", Value::Null).unwrap_err(),
+            "unknown command missing"
+        );
+    }
+
+    #[test]
+    fn respond() {
+        let commands = commands();
+        assert_eq!(post(&commands, "add", b"[1, 2]"), (200, "3".into()));
+        assert_eq!(post(&commands, "reverse", b"abc"), (200, "cba".into()));
+        assert_eq!(post(&commands, "add", b"{").0, 400);
+        assert_eq!(post(&commands, "fail", b""), (500, "failed".into()));
+        assert_eq!(post(&commands, "panic", b"").0, 500);
+        assert_eq!(post(&commands, "missing", b"").0, 404);
+        // 命令名由页面编码后放在路径中
+        assert_eq!(
+            post(&commands, "file%2Fread%20all", b"abc"),
+            (200, "abc".into())
+        );
+        let request = Request::builder().body(Vec::new()).unwrap();
+        assert_eq!(commands.respond(&request, "add").unwrap().status(), 405);
+        let request = Request::builder()
+            .method(Method::POST)
+            .body(Vec::new())
+            .unwrap();
+        assert_eq!(commands.respond(&request, "add").unwrap().status(), 403);
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved prefix")]
+    fn reserved_names() {
+        Commands::new().register("window.minimize", |_: Value| Ok::<_, String>(Value::Null));
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved prefix")]
+    fn reserved_binary_names() {
+        Commands::new().register_binary("window.read", |body: Vec<u8>| Ok::<_, String>(body));
+    }
+
+    #[test]
+    fn replies() {
+        assert_eq!(
+            reply(1, Ok(json!({ "a": 1 }))),
+            r#"window.neutauri.__resolve(1, true, {"a":1});"#
+        );
+        assert_eq!(
+            reply(2, Err("it's \"broken\"".into())),
+            r#"window.neutauri.__resolve(2, false, "it's \"broken\"");"#
+        );
+        let invoke = serde_json::from_str::<Invoke>(r#"{"id": 3, "cmd": "add"}"#).unwrap();
+        assert_eq!(
+            (invoke.id, invoke.cmd.as_str(), invoke.args),
+            (3, "add", Value::Null)
+        );
+    }
+
+    #[test]
+    fn pool() {
+        let pool = Pool::new();
+        let (sender, receiver) = mpsc::channel();
+        for i in 0..WORKERS * 2 {
+            let sender = sender.clone();
+            pool.execute(move || sender.send(i).unwrap());
+        }
+        let mut results = (0..WORKERS * 2)
+            .map(|_| receiver.recv().unwrap())
+            .collect::<Vec<_>>();
+        results.sort();
+        assert_eq!(results, (0..WORKERS * 2).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,541 @@
+use neutauri_data as data;
+use std::{borrow::Cow, io, path::PathBuf, sync::Arc};
+use wry::{
+    application::{
+        dpi::{PhysicalSize, Size},
+        event::{Event, WindowEvent},
+        event_loop::{ControlFlow, EventLoopBuilder},
+        window::{Fullscreen, Icon, Window, WindowBuilder},
+    },
+    http::{header, HeaderValue, Request, Response},
+    webview::{WebContext, WebViewBuilder},
+};
+
+mod asset;
+mod events;
+mod hooks;
+mod ipc;
+mod server;
+mod window;
+
+pub use asset::{respond, Asset, AssetSource, Layered, LocalDir};
+pub use hooks::Hooks;
+pub use ipc::Commands;
+
+// 从其他线程发往事件循环的消息
+enum UserEvent {
+    EvaluateScript(String),
+    // 页面同意关闭窗口或调用了 window.neutauri.window.close()
+    Close,
+    // 页面收到了关闭请求或取消了关闭，不再按超时退出
+    CloseAnswered,
+}
+
+pub struct App {
+    window_attr: data::WindowAttr,
+    webview_attr: data::WebViewAttr,
+    source: Arc<dyn AssetSource>,
+    identifier: String,
+    devtools: bool,
+    hooks: Hooks,
+}
+
+impl App {
+    pub fn new<S: AssetSource + 'static>(
+        window_attr: data::WindowAttr,
+        webview_attr: data::WebViewAttr,
+        source: S,
+    ) -> Self {
+        Self {
+            window_attr,
+            webview_attr,
+            source: Arc::new(source),
+            identifier: "neutauri_app".to_string(),
+            devtools: false,
+            hooks: Hooks::default(),
+        }
+    }
+
+    // 数据目录的名称，localStorage 等数据保存在系统配置目录下的同名目录中
+    pub fn with_identifier<S: Into<String>>(mut self, identifier: S) -> Self {
+        self.identifier = identifier.into();
+        self
+    }
+
+    // 开启开发者工具时保留右键菜单，以便使用“检查元素”
+    pub fn with_devtools(mut self, devtools: bool) -> Self {
+        self.devtools = devtools;
+        self
+    }
+
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    pub fn run(self) -> wry::Result<()> {
+        // 隐藏后只有嵌入方的回调能再显示窗口
+        if self.window_attr.close_behavior == data::CloseBehavior::Hide
+            && self.hooks.window_event.is_empty()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "close_behavior = \"hide\" needs an on_window_event hook that shows the window again",
+            )
+            .into());
+        }
+        let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
+        let proxy = event_loop.create_proxy();
+        let monitor_size = event_loop
+            .primary_monitor()
+            .unwrap_or_else(|| {
+                event_loop
+                    .available_monitors()
+                    .next()
+                    .expect("no monitor found")
+            })
+            .size();
+        let window = window_builder(&self.window_attr, monitor_size)?.build(&event_loop)?;
+
+        let webview_builder = WebViewBuilder::new(window)?;
+        let nonce = data::csp_nonce()?;
+        let drag = window::Drag::new(
+            &self.window_attr,
+            &self.webview_attr.window_api,
+            self.window_attr
+                .min_inner_size
+                .map(|size| physical_size(size, monitor_size)),
+            self.window_attr
+                .max_inner_size
+                .map(|size| physical_size(size, monitor_size)),
+        );
+        // 页面中的脚本动态插入 <script>/<style> 时，需要把 window.neutauri.nonce 赋给元素的 nonce 属性
+        let nonce_script = format!(
+            r#"Object.defineProperty(window.neutauri = window.neutauri || {{}}, 'nonce', {{ value: {} }});"#,
+            serde_json::Value::from(nonce.as_str())
+        );
+        let mut scripts = vec![
+            ipc::SCRIPT.to_string(),
+            events::SCRIPT.to_string(),
+            window::SCRIPT.to_string(),
+            drag.script(),
+        ];
+        if self.webview_attr.csp.is_some() {
+            scripts.insert(0, nonce_script);
+        }
+        if !self.window_attr.visible {
+            scripts.push(
+                r#"window.addEventListener('load', function(event) { window.ipc.postMessage('show_window'); });"#
+                    .to_string(),
+            );
+        }
+        if !self.devtools {
+            scripts.push(
+                r#"window.oncontextmenu = (event) => { event.preventDefault(); }"#.to_string(),
+            );
+        }
+        let bootstrap = bootstrap(&scripts);
+        let csp = self.webview_attr.csp.as_ref().map(|csp| PageCsp {
+            policy: data::csp_with_nonce(csp, &nonce),
+            meta: self.webview_attr.csp_meta,
+            nonce: nonce.clone(),
+            bootstrap: bootstrap.clone(),
+        });
+        let Hooks {
+            ready: ready_hooks,
+            ipc: ipc_hooks,
+            window_event: mut window_event_hooks,
+            commands,
+        } = self.hooks;
+        let commands = Arc::new(commands);
+        let source = self.source.clone();
+        let webview_attr = self.webview_attr.clone();
+        let invoke_commands = commands.clone();
+        let handler = Arc::new(
+            move |request: &Request<Vec<u8>>| -> wry::http::Result<Response<Vec<u8>>> {
+                let path = request.uri().path();
+                if let Some(name) = path.strip_prefix(ipc::INVOKE_PREFIX) {
+                    return invoke_commands.respond(request, name);
+                }
+                let response = respond(source.as_ref(), &webview_attr, path)?;
+                Ok(with_csp(response, csp.as_ref()))
+            },
+        );
+        let server = match self.webview_attr.serve_mode {
+            data::ServeMode::Protocol => None,
+            data::ServeMode::Localhost => {
+                let handler = handler.clone();
+                // token 与 nonce 一样，每次启动随机生成
+                let port_file = data_dir(&self.identifier).map(|dir| dir.join("localhost_port"));
+                Some(server::spawn(
+                    data::csp_nonce()?,
+                    port_file.as_deref(),
+                    move |request| handler(request),
+                )?)
+            }
+        };
+        let protocol = self.webview_attr.protocol.clone();
+        let entry_uri = |path: &str| match &server {
+            Some(server) => server.entry_uri(path),
+            None => protocol.uri(path),
+        };
+        let webview_builder = match &self.webview_attr.url {
+            Some(url) => {
+                if url.starts_with('/') {
+                    webview_builder.with_url(&entry_uri(url))?
+                } else {
+                    webview_builder.with_url(url)?
+                }
+            }
+            None => webview_builder.with_url(&entry_uri("/index.html"))?,
+        };
+        let webview_builder = match &self.webview_attr.html {
+            Some(html) => webview_builder.with_html(html)?,
+            None => webview_builder,
+        };
+        let webview_builder = webview_builder.with_initialization_script(&bootstrap);
+        let webview_builder = match &self.webview_attr.initialization_script {
+            Some(script) => webview_builder.with_initialization_script(script),
+            None => webview_builder,
+        };
+        let mut web_context = web_context(&self.identifier);
+        let webview_builder = webview_builder
+            .with_clipboard(true)
+            .with_visible(self.window_attr.visible)
+            .with_transparent(self.window_attr.transparent)
+            .with_web_context(&mut web_context);
+        let webview_builder = match server {
+            Some(_) => webview_builder,
+            None => webview_builder.with_custom_protocol(protocol.scheme.clone(), move |request| {
+                handler(request)
+                    .map(|response| response.map(Cow::Owned))
+                    .map_err(|e| e.into())
+            }),
+        };
+        let window_api = self.webview_attr.window_api.clone();
+        let pool = ipc::Pool::new();
+        let webview = webview_builder
+            .with_ipc_handler(move |window: &Window, req: String| {
+                // 不是合法调用的 JSON 消息同样交给嵌入方的回调
+                let invoke = match req.starts_with('{') {
+                    true => serde_json::from_str::<ipc::Invoke>(&req).ok(),
+                    false => None,
+                };
+                match (req.as_str(), invoke) {
+                    ("show_window", _) => window.set_visible(true),
+                    ("close_window", _) => proxy.send_event(UserEvent::Close).unwrap_or_default(),
+                    ("close_ack" | "close_prevented", _) => proxy
+                        .send_event(UserEvent::CloseAnswered)
+                        .unwrap_or_default(),
+                    // 拖动消息总是在这里处理，有边框时直接丢弃，不会落到嵌入方的回调
+                    _ if drag.handle(window, &req) => (),
+                    (_, Some(invoke)) if invoke.cmd.starts_with(window::PREFIX) => {
+                        let operation = &invoke.cmd[window::PREFIX.len()..];
+                        let result = window::invoke(window, &window_api, operation, invoke.args);
+                        proxy
+                            .send_event(UserEvent::EvaluateScript(ipc::reply(invoke.id, result)))
+                            .unwrap_or_default();
+                    }
+                    (_, Some(invoke)) => {
+                        let commands = commands.clone();
+                        let proxy = proxy.clone();
+                        pool.execute(move || {
+                            let result = commands.invoke(&invoke.cmd, invoke.args);
+                            let script = ipc::reply(invoke.id, result);
+                            proxy
+                                .send_event(UserEvent::EvaluateScript(script))
+                                .unwrap_or_default();
+                        });
+                    }
+                    (_, None) => {
+                        for hook in &ipc_hooks {
+                            if hook(window, &req) {
+                                break;
+                            }
+                        }
+                    }
+                };
+            })
+            .with_devtools(self.devtools)
+            .build()?;
+        for hook in ready_hooks {
+            hook(&webview);
+        }
+
+        let mut close = window::Close::new(self.window_attr.close_behavior);
+        event_loop.run(move |event, _, control_flow| {
+            *control_flow = ControlFlow::Wait;
+            close.wait(control_flow);
+
+            match event {
+                Event::WindowEvent { event, .. } => {
+                    if let WindowEvent::CloseRequested = event {
+                        close.request(control_flow);
+                    } else if let Some(script) = events::window_event(&event) {
+                        webview.evaluate_script(&script).unwrap_or_default();
+                    }
+                    for hook in &mut window_event_hooks {
+                        hook(&event, &webview, control_flow);
+                    }
+                    if let WindowEvent::CloseRequested = event {
+                        close.finish(&webview, control_flow);
+                    }
+                }
+                Event::UserEvent(UserEvent::EvaluateScript(script)) => {
+                    webview.evaluate_script(&script).unwrap_or_default()
+                }
+                Event::UserEvent(UserEvent::Close) => *control_flow = ControlFlow::Exit,
+                Event::UserEvent(UserEvent::CloseAnswered) => close.answer(),
+                Event::GlobalShortcutEvent(id) => {
+                    let script = events::emit("shortcut", serde_json::json!({ "id": id.0 }));
+                    webview.evaluate_script(&script).unwrap_or_default();
+                    // 兼容旧版本的全局函数
+                    webview
+                        .evaluate_script(&format!(
+                            "typeof GlobalShortcutEvent === 'function' && GlobalShortcutEvent({:})",
+                            id.0
+                        ))
+                        .unwrap_or_default();
+                }
+                _ => (),
+            }
+        });
+    }
+}
+
+fn window_builder(
+    window_attr: &data::WindowAttr,
+    monitor_size: PhysicalSize<u32>,
+) -> wry::Result<WindowBuilder> {
+    let window_builder = WindowBuilder::new()
+        .with_always_on_top(window_attr.always_on_top)
+        .with_decorations(window_attr.decorations)
+        .with_resizable(window_attr.resizable)
+        .with_title(window_attr.title.clone())
+        .with_maximized(window_attr.maximized)
+        .with_transparent(window_attr.transparent)
+        .with_visible(window_attr.visible);
+    let window_builder = match window_attr.fullscreen {
+        true => window_builder.with_fullscreen(Some(Fullscreen::Borderless(None))),
+        false => window_builder,
+    };
+    let window_builder = match window_attr.icon {
+        Some(ref icon) => window_builder.with_window_icon(Some(Icon::from_rgba(
+            icon.rgba.clone(),
+            icon.width,
+            icon.height,
+        )?)),
+        None => window_builder,
+    };
+    let window_builder = match window_attr.inner_size {
+        Some(size) => window_builder.with_inner_size(get_size(size, monitor_size)),
+        None => window_builder,
+    };
+    let window_builder = match window_attr.max_inner_size {
+        Some(size) => window_builder.with_max_inner_size(get_size(size, monitor_size)),
+        None => window_builder,
+    };
+    let window_builder = match window_attr.min_inner_size {
+        Some(size) => window_builder.with_min_inner_size(get_size(size, monitor_size)),
+        None => window_builder,
+    };
+    Ok(window_builder)
+}
+
+// 应用的数据目录，保存 webview 的本地存储等数据
+fn data_dir(identifier: &str) -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        let dir = match std::env::var("APPDATA") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => PathBuf::from("."),
+        };
+        Some(dir.join(identifier))
+    } else if cfg!(target_os = "linux") {
+        let dir = match std::env::var("XDG_CONFIG_DIR") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => match std::env::var("HOME") {
+                Ok(dir) => PathBuf::from(dir).join(".config"),
+                Err(_) => PathBuf::from("."),
+            },
+        };
+        Some(dir.join(identifier))
+    } else if cfg!(target_os = "macos") {
+        let dir = match std::env::var("HOME") {
+            Ok(dir) => PathBuf::from(dir).join("Library/Application Support/"),
+            Err(_) => PathBuf::from("."),
+        };
+        Some(dir.join(identifier))
+    } else {
+        None
+    }
+}
+
+fn web_context(identifier: &str) -> WebContext {
+    WebContext::new(data_dir(identifier))
+}
+
+// 内置脚本合并为一段，既作为初始化脚本，也以带 nonce 的内联脚本写入 html 响应，
+// 初始化脚本受页面 CSP 限制时由内联脚本补上，先执行的一方生效
+fn bootstrap(scripts: &[String]) -> String {
+    format!(
+        "if (!window.__neutauriBootstrap) {{\nObject.defineProperty(window, '__neutauriBootstrap', {{ value: true }});\n{}\n}}",
+        scripts.join(";\n")
+    )
+}
+
+// html 响应附加的 CSP，nonce 每次启动生成一次
+struct PageCsp {
+    policy: String,
+    meta: bool,
+    nonce: String,
+    bootstrap: String,
+}
+
+// html 响应统一附加 Content-Security-Policy，并在 <head> 中插入带 nonce 的内置脚本，
+// csp_meta 开启时同时插入与响应头相同的 meta 标签
+fn with_csp(mut response: Response<Vec<u8>>, csp: Option<&PageCsp>) -> Response<Vec<u8>> {
+    let html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|mime| mime.to_str().ok());
+    let html = matches!(html, Some(mime) if mime.starts_with("text/html"));
+    if let Some(csp) = csp.filter(|_| html) {
+        if let Ok(value) = HeaderValue::from_str(&csp.policy) {
+            response
+                .headers_mut()
+                .insert(header::CONTENT_SECURITY_POLICY, value);
+        }
+        let mut tags = match csp.meta {
+            true => data::csp_meta_tag(&csp.policy),
+            false => String::new(),
+        };
+        tags.push_str(&format!(
+            "<script nonce=\"{}\">{}</script>",
+            csp.nonce, csp.bootstrap
+        ));
+        let body = data::csp_inject(response.body(), &tags);
+        *response.body_mut() = body;
+    }
+    response
+}
+
+fn get_size(size: data::WindowSize, monitor_size: PhysicalSize<u32>) -> Size {
+    Size::Physical(physical_size(size, monitor_size))
+}
+
+fn physical_size(size: data::WindowSize, monitor_size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+    let (width, height) = match size {
+        data::WindowSize::Large => (
+            monitor_size.width as f64 * 0.7,
+            monitor_size.height as f64 * 0.7,
+        ),
+        data::WindowSize::Medium => (
+            monitor_size.width as f64 * 0.6,
+            monitor_size.height as f64 * 0.6,
+        ),
+        data::WindowSize::Small => (
+            monitor_size.width as f64 * 0.5,
+            monitor_size.height as f64 * 0.5,
+        ),
+        data::WindowSize::Fixed { width, height } => (width, height),
+        data::WindowSize::Scale { factor } => (
+            monitor_size.width as f64 * factor,
+            monitor_size.height as f64 * factor,
+        ),
+    };
+    PhysicalSize::new(width as u32, height as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(mime: &str) -> Response<Vec<u8>> {
+        Response::builder()
+            .header(header::CONTENT_TYPE, mime)
+            .body(Vec::new())
+            .unwrap()
+    }
+
+    #[test]
+    fn csp_header() {
+        let csp = PageCsp {
+            policy: data::csp_with_nonce("default-src 'self'", "abc"),
+            meta: false,
+            nonce: "abc".to_string(),
+            bootstrap: bootstrap(&[ipc::SCRIPT.to_string(), events::SCRIPT.to_string()]),
+        };
+        let html = with_csp(response("text/html; charset=utf-8"), Some(&csp));
+        assert_eq!(
+            html.headers()[header::CONTENT_SECURITY_POLICY],
+            "default-src 'self'; script-src 'self' 'nonce-abc'; style-src 'self' 'nonce-abc'"
+        );
+        let script = with_csp(response("text/javascript"), Some(&csp));
+        assert!(!script
+            .headers()
+            .contains_key(header::CONTENT_SECURITY_POLICY));
+        assert!(script.body().is_empty());
+        let html = with_csp(response("text/html"), None);
+        assert!(!html.headers().contains_key(header::CONTENT_SECURITY_POLICY));
+    }
+
+    // 严格的 script-src 下，内置脚本以带 nonce 的内联脚本执行，meta 标签使用同一份带 nonce 的策略
+    #[test]
+    fn csp_scripts() {
+        let scripts = [
+            ipc::SCRIPT.to_string(),
+            events::SCRIPT.to_string(),
+            window::SCRIPT.to_string(),
+        ];
+        let csp = PageCsp {
+            policy: data::csp_with_nonce("script-src 'self'", "abc"),
+            meta: true,
+            nonce: "abc".to_string(),
+            bootstrap: bootstrap(&scripts),
+        };
+        let mut html = response("text/html");
+        *html.body_mut() = b"<html><head><title>a</title></head></html>".to_vec();
+        let body = String::from_utf8(with_csp(html, Some(&csp)).into_body()).unwrap();
+        let meta = "<meta http-equiv=\"Content-Security-Policy\" content=\"script-src 'self' 'nonce-abc'\">";
+        let script = format!("<script nonce=\"abc\">{}</script>", csp.bootstrap);
+        assert_eq!(
+            body,
+            format!(
+                "<html><head>{}{}<title>a</title></head></html>",
+                meta, script
+            )
+        );
+        // 内置脚本中不能出现提前结束内联脚本的 </script>
+        assert_eq!(body.matches("</script>").count(), 1);
+        for script in scripts {
+            assert!(csp.bootstrap.contains(&script));
+        }
+    }
+
+    #[test]
+    fn window_sizes() {
+        let monitor = PhysicalSize::new(2000, 1000);
+        assert_eq!(
+            get_size(data::WindowSize::Small, monitor),
+            Size::Physical(PhysicalSize::new(1000, 500))
+        );
+        assert_eq!(
+            get_size(data::WindowSize::Large, monitor),
+            Size::Physical(PhysicalSize::new(1400, 700))
+        );
+        assert_eq!(
+            get_size(data::WindowSize::Scale { factor: 0.25 }, monitor),
+            Size::Physical(PhysicalSize::new(500, 250))
+        );
+        assert_eq!(
+            get_size(
+                data::WindowSize::Fixed {
+                    width: 640.0,
+                    height: 480.0
+                },
+                monitor
+            ),
+            Size::Physical(PhysicalSize::new(640, 480))
+        );
+    }
+}
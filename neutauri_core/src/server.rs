@@ -0,0 +1,300 @@
+use std::{fs, io, path::Path, sync::Arc};
+use wry::http::{Request, Response};
+
+const TOKEN_NAME: &str = "neutauri_token";
+// 处理请求的线程数，页面的并发请求超出时排队等待
+const WORKERS: usize = 4;
+
+pub(crate) struct Server {
+    pub(crate) origin: String,
+    token: String,
+}
+
+impl Server {
+    // 带上 token 的入口地址，首次访问后 token 会被写入 cookie
+    pub(crate) fn entry_uri(&self, path: &str) -> String {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        format!(
+            "{}{}{}{}={}",
+            self.origin, path, separator, TOKEN_NAME, self.token
+        )
+    }
+}
+
+// 在 127.0.0.1 上提供资源，只响应携带本次启动 token 的请求
+pub(crate) fn spawn<F>(token: String, port_file: Option<&Path>, handler: F) -> io::Result<Server>
+where
+    F: Fn(&Request<Vec<u8>>) -> wry::http::Result<Response<Vec<u8>>> + Send + Sync + 'static,
+{
+    let (server, port) = bind(port_file)?;
+    let origin = format!("http://127.0.0.1:{}", port);
+    let server = Arc::new(server);
+    let handler = Arc::new(handler);
+    let cookie = format!("{}={}", TOKEN_NAME, token);
+    for _ in 0..WORKERS {
+        let server = server.clone();
+        let handler = handler.clone();
+        let cookie = cookie.clone();
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let response = respond(&mut request, &cookie, handler.as_ref());
+                request.respond(response).unwrap_or_default();
+            }
+        });
+    }
+    Ok(Server { origin, token })
+}
+
+// 优先使用上次保存的端口，使页面的 origin 与本地存储在多次启动间保持不变；
+// 端口被占用或没有保存过时改用随机端口，并保存下来供下次使用
+fn bind(port_file: Option<&Path>) -> io::Result<(tiny_http::Server, u16)> {
+    let saved = port_file
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|port| port.trim().parse::<u16>().ok())
+        .filter(|&port| port != 0);
+    let server = match saved.and_then(|port| tiny_http::Server::http(("127.0.0.1", port)).ok()) {
+        Some(server) => server,
+        None => tiny_http::Server::http("127.0.0.1:0")
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?,
+    };
+    let port = match server.server_addr().to_ip() {
+        Some(addr) => addr.port(),
+        None => return Err(io::Error::new(io::ErrorKind::Other, "no local address")),
+    };
+    if let Some(path) = port_file.filter(|_| saved != Some(port)) {
+        // 保存失败只会导致下次换一个端口
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).unwrap_or_default();
+        }
+        fs::write(path, port.to_string()).unwrap_or_default();
+    }
+    Ok((server, port))
+}
+
+fn respond<F>(
+    request: &mut tiny_http::Request,
+    cookie: &str,
+    handler: &F,
+) -> tiny_http::Response<io::Cursor<Vec<u8>>>
+where
+    F: Fn(&Request<Vec<u8>>) -> wry::http::Result<Response<Vec<u8>>>,
+{
+    let url = request.url().to_string();
+    let (path, query) = match url.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (url.as_str(), None),
+    };
+    let params = query
+        .map(|query| query.split('&').collect::<Vec<_>>())
+        .unwrap_or_default();
+    // 带有 token 的入口请求：写入 cookie 后重定向到去掉 token 的地址
+    if params.iter().any(|param| constant_time_eq(param, cookie)) {
+        let rest = params
+            .iter()
+            .filter(|param| !constant_time_eq(param, cookie))
+            .copied()
+            .collect::<Vec<_>>();
+        let location = match rest.is_empty() {
+            true => path.to_string(),
+            false => format!("{}?{}", path, rest.join("&")),
+        };
+        let mut response = tiny_http::Response::from_data(Vec::new()).with_status_code(302);
+        add_header(&mut response, "Location", &location);
+        add_header(
+            &mut response,
+            "Set-Cookie",
+            &format!("{}; Path=/; HttpOnly; SameSite=Strict", cookie),
+        );
+        return response;
+    }
+    let authorized = request
+        .headers()
+        .iter()
+        .filter(|header| header.field.equiv("Cookie"))
+        .flat_map(|header| header.value.as_str().split(';'))
+        .any(|value| constant_time_eq(value.trim(), cookie));
+    if !authorized {
+        return tiny_http::Response::from_data(b"403 Forbidden".to_vec()).with_status_code(403);
+    }
+    let (parts, body) = match http_request(request, path).and_then(|r| handler(&r)) {
+        Ok(response) => response.into_parts(),
+        Err(_) => {
+            return tiny_http::Response::from_data(b"500 Internal Server Error".to_vec())
+                .with_status_code(500)
+        }
+    };
+    let mut response = tiny_http::Response::from_data(body).with_status_code(parts.status.as_u16());
+    for (name, value) in parts.headers.iter() {
+        if let Ok(value) = value.to_str() {
+            add_header(&mut response, name.as_str(), value);
+        }
+    }
+    response
+}
+
+// 转换为与自定义协议相同的请求类型，路径不含查询参数
+fn http_request(
+    request: &mut tiny_http::Request,
+    path: &str,
+) -> wry::http::Result<Request<Vec<u8>>> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .read_to_end(&mut body)
+        .unwrap_or_default();
+    let mut builder = Request::builder()
+        .method(request.method().as_str())
+        .uri(path);
+    for header in request.headers() {
+        builder = builder.header(header.field.as_str().as_str(), header.value.as_str());
+    }
+    builder.body(body)
+}
+
+// 比较耗时与内容无关，避免通过响应时间逐字节猜出 token
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn add_header(response: &mut tiny_http::Response<io::Cursor<Vec<u8>>>, name: &str, value: &str) {
+    if let Ok(header) = tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+        response.add_header(header);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+    };
+
+    // 发送一个请求并返回完整的响应文本
+    fn send(server: &Server, request: &str) -> String {
+        let address = server.origin.trim_start_matches("http://");
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn get(server: &Server, target: &str, cookie: Option<&str>) -> String {
+        let cookie = match cookie {
+            Some(cookie) => format!("Cookie: a=b; {}\r\n", cookie),
+            None => String::new(),
+        };
+        send(
+            server,
+            &format!(
+                "GET {} HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+                target, cookie
+            ),
+        )
+    }
+
+    fn server() -> Server {
+        spawn("secret".to_string(), None, |request| {
+            Response::builder()
+                .status(200)
+                .header("X-Method", request.method().as_str())
+                .body(
+                    format!(
+                        "{} {}",
+                        request.uri().path(),
+                        String::from_utf8_lossy(request.body())
+                    )
+                    .into_bytes(),
+                )
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn token() {
+        let server = server();
+        let entry = server.entry_uri("/index.html?a=1");
+        assert!(entry.ends_with("/index.html?a=1&neutauri_token=secret"));
+        let response = get(&server, "/index.html?a=1&neutauri_token=secret", None);
+        assert!(response.starts_with("HTTP/1.1 302"), "{}", response);
+        assert!(response.contains("Location: /index.html?a=1"));
+        assert!(response
+            .contains("Set-Cookie: neutauri_token=secret; Path=/; HttpOnly; SameSite=Strict"));
+        let response = get(&server, "/index.html", Some("neutauri_token=secret"));
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("/index.html "));
+    }
+
+    #[test]
+    fn unauthorized() {
+        let server = server();
+        for (target, cookie) in [
+            ("/index.html", None),
+            ("/index.html", Some("neutauri_token=secreT")),
+            ("/index.html", Some("neutauri_token=secret2")),
+            ("/index.html?neutauri_token=secre", None),
+        ] {
+            let response = get(&server, target, cookie);
+            assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+        }
+    }
+
+    #[test]
+    fn request_body() {
+        let server = server();
+        let response = send(
+            &server,
+            "POST /echo HTTP/1.1\r\nHost: localhost\r\nCookie: neutauri_token=secret\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+        );
+        assert!(response.contains("x-method: POST"), "{}", response);
+        assert!(response.ends_with("/echo hello"));
+    }
+
+    fn port(server: &Server) -> u16 {
+        server.origin.rsplit(':').next().unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn saved_port() {
+        let dir = std::env::temp_dir().join(format!("neutauri-port-{}", std::process::id()));
+        let path = dir.join("port");
+        let spawn = || {
+            spawn("secret".to_string(), Some(&path), |_| {
+                Response::builder().body(Vec::new())
+            })
+            .unwrap()
+        };
+        // 第一次启动保存随机端口
+        let first = spawn();
+        assert_eq!(fs::read_to_string(&path).unwrap(), port(&first).to_string());
+        // 保存的端口被占用时改用其他端口并更新保存的端口
+        let second = spawn();
+        assert_ne!(port(&second), port(&first));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            port(&second).to_string()
+        );
+        // 保存的端口空闲时继续使用
+        let free = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        fs::write(&path, free.to_string()).unwrap();
+        assert_eq!(port(&spawn()), free);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compare() {
+        assert!(constant_time_eq("abc", "abc"));
+        assert!(!constant_time_eq("abc", "abd"));
+        assert!(!constant_time_eq("abc", "ab"));
+        assert!(constant_time_eq("", ""));
+    }
+}
@@ -0,0 +1,584 @@
+use crate::events;
+use neutauri_data as data;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+use wry::{
+    application::{
+        dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize},
+        event_loop::ControlFlow,
+        window::{Fullscreen, Window},
+    },
+    webview::WebView,
+};
+
+// 以该前缀开头的命令是内置的窗口操作，在主线程中执行
+pub(crate) const PREFIX: &str = "window.";
+
+// window.neutauri.window 上的方法，尺寸与坐标均为逻辑像素
+pub(crate) const SCRIPT: &str = r#"(function () {
+  var neutauri = window.neutauri = window.neutauri || {};
+  var call = function (operation, args) { return neutauri.invoke('window.' + operation, args); };
+  neutauri.window = {
+    minimize: function () { return call('minimize'); },
+    maximize: function () { return call('maximize'); },
+    restore: function () { return call('restore'); },
+    show: function () { return call('show'); },
+    hide: function () { return call('hide'); },
+    focus: function () { return call('focus'); },
+    center: function () { return call('center'); },
+    setTitle: function (title) { return call('set_title', { title: title }); },
+    setFullscreen: function (fullscreen) { return call('set_fullscreen', { fullscreen: fullscreen }); },
+    setAlwaysOnTop: function (alwaysOnTop) { return call('set_always_on_top', { always_on_top: alwaysOnTop }); },
+    setSize: function (width, height) { return call('set_size', { width: width, height: height }); },
+    setPosition: function (x, y) { return call('set_position', { x: x, y: y }); },
+    state: function () { return call('state'); },
+    // 不受 window_api 限制，与点击关闭按钮相同，但不再触发 close-requested
+    close: function () { window.ipc.postMessage('close_window'); }
+  };
+  // close_behavior 为 ask_js 时，监听函数调用 event.preventDefault() 可以取消关闭；
+  // 先确认收到请求，监听函数中的 confirm() 等对话框不会因超时被强制退出
+  neutauri.__requestClose = function () {
+    window.ipc.postMessage('close_ack');
+    var prevented = false;
+    neutauri.__emit('close-requested', { preventDefault: function () { prevented = true; } });
+    window.ipc.postMessage(prevented ? 'close_prevented' : 'close_window');
+  };
+})();"#;
+
+// 页面确认收到 close-requested 的最长时间，页面没有响应（如脚本卡死）时直接退出
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(3);
+
+// 按下标记了 data-neutauri-drag-region 的元素或其子元素时拖动窗口，双击切换最大化，
+// 区域内的链接、按钮、输入框等交互元素与标记了 data-neutauri-no-drag 的元素除外；
+// border 大于 0 时，在距窗口边缘 border 像素内拖动可以调整窗口大小
+const DRAG_SCRIPT: &str = r#"(function (border) {
+  var post = function (message) { window.ipc.postMessage(message); };
+  var edge = function (event) {
+    if (border <= 0) return '';
+    var y = event.clientY < border ? 'n' : event.clientY >= window.innerHeight - border ? 's' : '';
+    var x = event.clientX < border ? 'w' : event.clientX >= window.innerWidth - border ? 'e' : '';
+    return y + x;
+  };
+  var interactive = 'a[href], button, input, select, textarea, label, summary, [contenteditable]:not([contenteditable="false"]), [data-neutauri-no-drag]';
+  var dragRegion = function (target) {
+    var region = target.closest ? target.closest('[data-neutauri-drag-region]') : null;
+    if (!region) return false;
+    var control = target.closest(interactive);
+    return !(control && region.contains(control));
+  };
+  var resizing = null, pending = null, cursor = false;
+  document.addEventListener('mousedown', function (event) {
+    if (event.button !== 0) return;
+    var direction = edge(event);
+    if (direction) {
+      event.preventDefault();
+      resizing = { direction: direction, x: event.screenX, y: event.screenY };
+      post('resize_start');
+    } else if (dragRegion(event.target)) {
+      event.preventDefault();
+      post(event.detail === 2 ? 'toggle_maximize' : 'drag_window');
+    }
+  });
+  document.addEventListener('mousemove', function (event) {
+    if (resizing) {
+      // 每帧最多发送一次
+      if (pending === null) requestAnimationFrame(function () { post(pending); pending = null; });
+      pending = 'resize_window ' + resizing.direction + ' ' + (event.screenX - resizing.x) + ' ' + (event.screenY - resizing.y);
+      return;
+    }
+    var direction = edge(event);
+    if (direction || cursor) {
+      document.documentElement.style.cursor = direction ? direction + '-resize' : '';
+      cursor = direction !== '';
+    }
+  });
+  document.addEventListener('mouseup', function () { resizing = null; });
+})"#;
+
+// 无边框窗口的拖动与调整大小
+pub(crate) struct Drag {
+    // 有边框时由系统负责拖动与调整大小，不处理页面的消息
+    enabled: bool,
+    border: u32,
+    // 双击切换最大化同样受 window_api 限制
+    maximize: bool,
+    // 与窗口的 min_inner_size/max_inner_size 一致的物理像素尺寸
+    min_size: (f64, f64),
+    max_size: (f64, f64),
+    // 开始调整大小时窗口的位置与尺寸
+    start: Cell<Option<(PhysicalPosition<i32>, PhysicalSize<u32>)>>,
+}
+
+impl Drag {
+    pub(crate) fn new(
+        window_attr: &data::WindowAttr,
+        window_api: &[String],
+        min_size: Option<PhysicalSize<u32>>,
+        max_size: Option<PhysicalSize<u32>>,
+    ) -> Self {
+        let enabled = !window_attr.decorations;
+        let border = match enabled && window_attr.resizable {
+            true => window_attr.resize_border,
+            false => 0,
+        };
+        let size = |size: PhysicalSize<u32>| (size.width as f64, size.height as f64);
+        Self {
+            enabled,
+            border,
+            maximize: window_attr.resizable && check(window_api, "maximize").is_ok(),
+            min_size: min_size.map_or((0.0, 0.0), size),
+            max_size: max_size.map_or((f64::INFINITY, f64::INFINITY), size),
+            start: Cell::new(None),
+        }
+    }
+
+    pub(crate) fn script(&self) -> String {
+        format!("{}({});", DRAG_SCRIPT, self.border)
+    }
+
+    // 返回 false 表示不是拖动相关的消息；有边框时拖动消息直接丢弃，不交给嵌入方的回调
+    pub(crate) fn handle(&self, window: &Window, message: &str) -> bool {
+        if !is_drag_message(message) {
+            return false;
+        }
+        if !self.enabled {
+            return true;
+        }
+        match message {
+            "drag_window" => window.drag_window().unwrap_or_default(),
+            "toggle_maximize" if self.maximize => window.set_maximized(!window.is_maximized()),
+            "toggle_maximize" => (),
+            "resize_start" => self.start.set(
+                window
+                    .outer_position()
+                    .ok()
+                    .map(|position| (position, window.inner_size())),
+            ),
+            _ => {
+                if let Some(args) = message.strip_prefix("resize_window ") {
+                    self.resize(window, args)
+                }
+            }
+        }
+        true
+    }
+
+    // args 为 "<方向> <dx> <dy>"，方向由 n、s、w、e 组成，偏移量为相对开始时的逻辑像素
+    fn resize(&self, window: &Window, args: &str) {
+        let (position, size) = match self.start.get() {
+            Some(start) if self.border > 0 && !window.is_maximized() => start,
+            _ => return,
+        };
+        let scale_factor = window.scale_factor();
+        let mut args = args.split(' ');
+        let direction = args.next().unwrap_or_default();
+        let mut delta = || {
+            args.next()
+                .and_then(|arg| arg.parse::<f64>().ok())
+                .unwrap_or_default()
+                * scale_factor
+        };
+        let (dx, dy) = (delta(), delta());
+        // 尺寸先按窗口的限制截断，系统不会再调整，移动 w/n 边时右下角保持不动
+        let border = (self.border * 2) as f64 * scale_factor;
+        let side =
+            |start: char, end: char| match (direction.contains(start), direction.contains(end)) {
+                (true, _) => -1,
+                (_, true) => 1,
+                _ => 0,
+            };
+        let (x, width) = resize_axis(
+            position.x as f64,
+            size.width as f64,
+            dx,
+            side('w', 'e'),
+            (self.min_size.0.max(border), self.max_size.0),
+        );
+        let (y, height) = resize_axis(
+            position.y as f64,
+            size.height as f64,
+            dy,
+            side('n', 's'),
+            (self.min_size.1.max(border), self.max_size.1),
+        );
+        if direction.contains('w') || direction.contains('n') {
+            window.set_outer_position(PhysicalPosition::new(x as i32, y as i32));
+        }
+        window.set_inner_size(PhysicalSize::new(width as u32, height as u32));
+    }
+}
+
+// DRAG_SCRIPT 发送的消息
+fn is_drag_message(message: &str) -> bool {
+    matches!(message, "drag_window" | "toggle_maximize" | "resize_start")
+        || message.starts_with("resize_window ")
+}
+
+// 沿一个坐标轴调整大小，side 为 -1 时移动起始边（w/n），为 1 时移动结束边（e/s），
+// 返回新的起点与长度，长度限制在 limits 之间
+fn resize_axis(
+    origin: f64,
+    length: f64,
+    delta: f64,
+    side: i8,
+    (min, max): (f64, f64),
+) -> (f64, f64) {
+    let resized = match side {
+        1 => length + delta,
+        -1 => length - delta,
+        _ => return (origin, length),
+    };
+    let resized = resized.min(max).max(min);
+    match side {
+        -1 => (origin + length - resized, resized),
+        _ => (origin, resized),
+    }
+}
+
+// 按 close_behavior 处理关闭请求
+pub(crate) struct Close {
+    behavior: data::CloseBehavior,
+    // 等待页面响应的截止时间
+    deadline: Option<Instant>,
+}
+
+impl Close {
+    pub(crate) fn new(behavior: data::CloseBehavior) -> Self {
+        Self {
+            behavior,
+            deadline: None,
+        }
+    }
+
+    // 收到 CloseRequested 时在 on_window_event 回调之前调用，回调看到的是默认的 control_flow
+    pub(crate) fn request(&mut self, control_flow: &mut ControlFlow) {
+        match self.behavior {
+            data::CloseBehavior::Exit => *control_flow = ControlFlow::Exit,
+            data::CloseBehavior::Hide => (),
+            data::CloseBehavior::AskJs => {
+                let deadline = Instant::now() + CLOSE_TIMEOUT;
+                *control_flow = ControlFlow::WaitUntil(*self.deadline.get_or_insert(deadline));
+            }
+        }
+    }
+
+    // 回调之后调用，回调把 control_flow 改回 Wait 表示取消关闭，此时不再通知页面
+    pub(crate) fn finish(&mut self, webview: &WebView, control_flow: &mut ControlFlow) {
+        if !self.proceed(control_flow) {
+            return;
+        }
+        if self.behavior == data::CloseBehavior::Hide {
+            webview.window().set_visible(false);
+        }
+        let script = self.notify(control_flow);
+        webview.evaluate_script(&script).unwrap_or_default();
+    }
+
+    // 通知页面的脚本；Exit 时先等页面执行完 close-requested 的监听函数再退出，
+    // 页面没有响应时按超时退出
+    fn notify(&mut self, control_flow: &mut ControlFlow) -> String {
+        match self.behavior {
+            data::CloseBehavior::Exit => {
+                let deadline = Instant::now() + CLOSE_TIMEOUT;
+                *control_flow = ControlFlow::WaitUntil(*self.deadline.get_or_insert(deadline));
+                format!(
+                    "{} window.ipc.postMessage('close_window');",
+                    events::emit("close-requested", Value::Null)
+                )
+            }
+            data::CloseBehavior::Hide => events::emit("close-requested", Value::Null),
+            data::CloseBehavior::AskJs => "window.neutauri.__requestClose();".to_string(),
+        }
+    }
+
+    // Hide 不修改 control_flow，回调无法取消
+    fn proceed(&mut self, control_flow: &ControlFlow) -> bool {
+        if self.behavior != data::CloseBehavior::Hide && matches!(control_flow, ControlFlow::Wait) {
+            self.deadline = None;
+            return false;
+        }
+        true
+    }
+
+    // 页面确认收到了请求或取消了关闭，之后由页面决定是否退出
+    pub(crate) fn answer(&mut self) {
+        self.deadline = None;
+    }
+
+    // 每轮事件循环开始时调用，页面超时未响应则退出
+    pub(crate) fn wait(&self, control_flow: &mut ControlFlow) {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => *control_flow = ControlFlow::Exit,
+            Some(deadline) => *control_flow = ControlFlow::WaitUntil(deadline),
+            None => (),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SetTitle {
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct SetFullscreen {
+    fullscreen: bool,
+}
+
+#[derive(Deserialize)]
+struct SetAlwaysOnTop {
+    always_on_top: bool,
+}
+
+#[derive(Deserialize)]
+struct SetSize {
+    width: f64,
+    height: f64,
+}
+
+#[derive(Deserialize)]
+struct SetPosition {
+    x: f64,
+    y: f64,
+}
+
+// 只执行 window_api 中允许的操作
+pub(crate) fn invoke(
+    window: &Window,
+    allowed: &[String],
+    operation: &str,
+    args: Value,
+) -> Result<Value, String> {
+    check(allowed, operation)?;
+    match operation {
+        "minimize" => window.set_minimized(true),
+        "maximize" => window.set_maximized(true),
+        "restore" => {
+            window.set_minimized(false);
+            window.set_maximized(false);
+        }
+        "show" => window.set_visible(true),
+        "hide" => window.set_visible(false),
+        "focus" => window.set_focus(),
+        "center" => center(window),
+        "set_title" => window.set_title(&parse::<SetTitle>(operation, args)?.title),
+        "set_fullscreen" => match parse::<SetFullscreen>(operation, args)?.fullscreen {
+            true => window.set_fullscreen(Some(Fullscreen::Borderless(None))),
+            false => window.set_fullscreen(None),
+        },
+        "set_always_on_top" => {
+            window.set_always_on_top(parse::<SetAlwaysOnTop>(operation, args)?.always_on_top)
+        }
+        "set_size" => {
+            let size = parse::<SetSize>(operation, args)?;
+            window.set_inner_size(LogicalSize::new(size.width, size.height));
+        }
+        "set_position" => {
+            let position = parse::<SetPosition>(operation, args)?;
+            window.set_outer_position(LogicalPosition::new(position.x, position.y));
+        }
+        "state" => return Ok(state(window)),
+        _ => return Err(format!("unknown window operation {}", operation)),
+    }
+    Ok(Value::Null)
+}
+
+fn check(allowed: &[String], operation: &str) -> Result<(), String> {
+    match allowed.iter().any(|name| name == "*" || name == operation) {
+        true => Ok(()),
+        false => Err(format!(
+            "window.{} is not allowed, add \"{}\" to window_api in neutauri.toml",
+            operation, operation
+        )),
+    }
+}
+
+fn parse<T: DeserializeOwned>(operation: &str, args: Value) -> Result<T, String> {
+    serde_json::from_value(args)
+        .map_err(|e| format!("invalid arguments for window.{}: {}", operation, e))
+}
+
+// 移动到当前显示器的中央
+fn center(window: &Window) {
+    let monitor = match window.current_monitor() {
+        Some(monitor) => monitor,
+        None => return,
+    };
+    let (origin, screen, size) = (monitor.position(), monitor.size(), window.outer_size());
+    window.set_outer_position(PhysicalPosition::new(
+        origin.x + (screen.width as i32 - size.width as i32) / 2,
+        origin.y + (screen.height as i32 - size.height as i32) / 2,
+    ));
+}
+
+fn state(window: &Window) -> Value {
+    let scale_factor = window.scale_factor();
+    let size: LogicalSize<f64> = window.inner_size().to_logical(scale_factor);
+    let position: Option<LogicalPosition<f64>> = window
+        .outer_position()
+        .ok()
+        .map(|position| position.to_logical(scale_factor));
+    json!({
+        "maximized": window.is_maximized(),
+        "fullscreen": window.fullscreen().is_some(),
+        "visible": window.is_visible(),
+        "width": size.width,
+        "height": size.height,
+        "x": position.map(|position| position.x),
+        "y": position.map(|position| position.y),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resize_limits() {
+        let limits = (100.0, 500.0);
+        assert_eq!(resize_axis(10.0, 300.0, 50.0, 1, limits), (10.0, 350.0));
+        assert_eq!(resize_axis(10.0, 300.0, 50.0, -1, limits), (60.0, 250.0));
+        assert_eq!(resize_axis(10.0, 300.0, 50.0, 0, limits), (10.0, 300.0));
+        // 达到限制后起始边不再移动，结束边保持在 310
+        assert_eq!(resize_axis(10.0, 300.0, 250.0, -1, limits), (210.0, 100.0));
+        assert_eq!(
+            resize_axis(10.0, 300.0, -400.0, -1, limits),
+            (-190.0, 500.0)
+        );
+        assert_eq!(resize_axis(10.0, 300.0, 400.0, 1, limits), (10.0, 500.0));
+    }
+
+    #[test]
+    fn drag_options() {
+        let window_attr = |decorations: bool, resizable: bool| {
+            data::Config {
+                decorations,
+                resizable,
+                resize_border: 6,
+                ..Default::default()
+            }
+            .window_attr()
+            .unwrap()
+        };
+        let drag = Drag::new(&window_attr(true, true), &["*".to_string()], None, None);
+        assert!(!drag.enabled);
+        assert_eq!(drag.border, 0);
+        let drag = Drag::new(
+            &window_attr(false, true),
+            &["minimize".to_string()],
+            Some(PhysicalSize::new(200, 100)),
+            None,
+        );
+        assert!(drag.enabled && !drag.maximize);
+        assert_eq!(drag.border, 6);
+        assert_eq!(drag.min_size, (200.0, 100.0));
+        assert_eq!(drag.max_size.0, f64::INFINITY);
+        let drag = Drag::new(
+            &window_attr(false, false),
+            &["maximize".to_string()],
+            None,
+            None,
+        );
+        assert!(drag.enabled && !drag.maximize);
+        assert_eq!(drag.border, 0);
+        let drag = Drag::new(
+            &window_attr(false, true),
+            &["maximize".to_string()],
+            None,
+            None,
+        );
+        assert!(drag.maximize);
+    }
+
+    // 有边框时这些消息同样被拦下，其他消息交给嵌入方的回调
+    #[test]
+    fn drag_messages() {
+        for message in [
+            "drag_window",
+            "toggle_maximize",
+            "resize_start",
+            "resize_window se 10 -4",
+        ] {
+            assert!(is_drag_message(message), "{}", message);
+        }
+        for message in ["drag", "resize_window", "close_window", "{\"cmd\":\"x\"}"] {
+            assert!(!is_drag_message(message), "{}", message);
+        }
+        // 拖动区域内的交互元素不触发拖动
+        assert!(DRAG_SCRIPT.contains("closest('[data-neutauri-drag-region]')"));
+        assert!(DRAG_SCRIPT.contains("button, input"));
+    }
+
+    #[test]
+    fn close_requests() {
+        let mut control_flow = ControlFlow::Wait;
+        let mut close = Close::new(data::CloseBehavior::AskJs);
+        close.request(&mut control_flow);
+        assert!(matches!(control_flow, ControlFlow::WaitUntil(_)));
+        assert!(close.proceed(&control_flow));
+        // 页面确认收到请求后不再超时退出
+        close.answer();
+        let mut control_flow = ControlFlow::Wait;
+        close.wait(&mut control_flow);
+        assert!(matches!(control_flow, ControlFlow::Wait));
+        // 超时未响应
+        close.request(&mut control_flow);
+        close.deadline = Some(Instant::now());
+        close.wait(&mut control_flow);
+        assert!(matches!(control_flow, ControlFlow::Exit));
+        // 回调把 control_flow 改回 Wait 时取消关闭
+        let mut close = Close::new(data::CloseBehavior::AskJs);
+        close.request(&mut control_flow);
+        assert!(!close.proceed(&ControlFlow::Wait));
+        assert!(close.deadline.is_none());
+        let mut close = Close::new(data::CloseBehavior::Exit);
+        let mut control_flow = ControlFlow::Wait;
+        close.request(&mut control_flow);
+        assert!(matches!(control_flow, ControlFlow::Exit));
+        assert!(close.proceed(&control_flow));
+        assert!(!close.proceed(&ControlFlow::Wait));
+        // 先把 close-requested 发给页面，页面回复 close_window 或超时后才退出
+        let script = close.notify(&mut control_flow);
+        assert!(matches!(control_flow, ControlFlow::WaitUntil(_)));
+        assert!(
+            script.find("close-requested").unwrap() < script.find("close_window").unwrap(),
+            "{}",
+            script
+        );
+        close.deadline = Some(Instant::now());
+        close.wait(&mut control_flow);
+        assert!(matches!(control_flow, ControlFlow::Exit));
+        let mut close = Close::new(data::CloseBehavior::Hide);
+        let mut control_flow = ControlFlow::Wait;
+        close.request(&mut control_flow);
+        assert!(close.proceed(&control_flow));
+    }
+
+    #[test]
+    fn permissions() {
+        let allowed = ["minimize".to_string(), "set_title".to_string()];
+        assert!(check(&allowed, "minimize").is_ok());
+        assert!(check(&allowed, "set_title").is_ok());
+        assert_eq!(
+            check(&allowed, "hide").unwrap_err(),
+            "window.hide is not allowed, add \"hide\" to window_api in neutauri.toml"
+        );
+        assert!(check(&[], "minimize").is_err());
+        assert!(check(&["*".to_string()], "set_size").is_ok());
+    }
+
+    #[test]
+    fn arguments() {
+        let size = parse::<SetSize>("set_size", json!({ "width": 800, "height": 600.5 })).unwrap();
+        assert_eq!((size.width, size.height), (800.0, 600.5));
+        let error = parse::<SetTitle>("set_title", json!({ "name": "a" })).err();
+        assert!(error
+            .unwrap()
+            .starts_with("invalid arguments for window.set_title"));
+        assert!(parse::<SetFullscreen>("set_fullscreen", Value::Null).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+#[cfg(feature = "runtime")]
+use std::io::Write;
+use std::{
+    fs,
+    io::{self, Read, Result},
+    path::Path,
+};
+
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+fn detect(path: &Path) -> Option<Format> {
+    let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+    if name.ends_with(".zip") {
+        Some(Format::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else if name.ends_with(".tar") {
+        Some(Format::Tar)
+    } else {
+        None
+    }
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    path.is_file() && detect(path).is_some()
+}
+
+// 返回归档中的所有文件，路径以 / 分隔且不含开头的 /，目录项会被跳过
+pub(crate) fn read(path: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    match detect(path) {
+        Some(Format::Zip) => {
+            let mut archive = zip::ZipArchive::new(fs::File::open(path)?)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            for i in 0..archive.len() {
+                let mut file = archive
+                    .by_index(i)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                if file.is_dir() {
+                    continue;
+                }
+                let name = entry_name(file.name())?;
+                let mut data = Vec::with_capacity(file.size() as usize);
+                file.read_to_end(&mut data)?;
+                files.push((name, data));
+            }
+        }
+        Some(Format::Tar) => read_tar(fs::File::open(path)?, &mut files)?,
+        Some(Format::TarGz) => read_tar(
+            flate2::read::GzDecoder::new(fs::File::open(path)?),
+            &mut files,
+        )?,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported archive {}", path.display()),
+            ))
+        }
+    }
+    Ok(files)
+}
+
+fn read_tar<R: Read>(reader: R, files: &mut Vec<(String, Vec<u8>)>) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry_name(&entry.path()?.to_string_lossy())?;
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+        files.push((name, data));
+    }
+    Ok(())
+}
+
+// 去掉 "./" 与多余的分隔符，拒绝指向归档之外的路径
+fn entry_name(name: &str) -> Result<String> {
+    let mut parts = Vec::new();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => (),
+            ".." => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid path \"{}\" in archive", name),
+                ))
+            }
+            part => parts.push(part),
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+// 按扩展名写出 zip、tar 或 tar.gz
+#[cfg(feature = "runtime")]
+pub(crate) fn write(path: &Path, files: &[(String, Vec<u8>)]) -> Result<()> {
+    let format = detect(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "unsupported archive {}, expected .zip, .tar or .tar.gz",
+                path.display()
+            ),
+        )
+    })?;
+    let target = fs::File::create(path)?;
+    match format {
+        Format::Zip => {
+            let mut archive = zip::ZipWriter::new(target);
+            let options = zip::write::FileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated);
+            for (name, data) in files {
+                archive
+                    .start_file(name.trim_start_matches('/'), options)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                archive.write_all(data)?;
+            }
+            archive
+                .finish()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        }
+        Format::Tar => write_tar(target, files)?.flush()?,
+        Format::TarGz => {
+            let encoder = flate2::write::GzEncoder::new(target, flate2::Compression::default());
+            write_tar(encoder, files)?.finish()?.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "runtime")]
+fn write_tar<W: Write>(writer: W, files: &[(String, Vec<u8>)]) -> Result<W> {
+    let mut archive = tar::Builder::new(writer);
+    for (name, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        archive.append_data(&mut header, name.trim_start_matches('/'), data.as_slice())?;
+    }
+    archive.into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_names() {
+        assert_eq!(entry_name("./a//b/c.js").unwrap(), "a/b/c.js");
+        assert_eq!(entry_name("a\\b.js").unwrap(), "a/b.js");
+        assert!(entry_name("../a.js").is_err());
+        assert!(entry_name("a/../../b.js").is_err());
+    }
+
+    #[cfg(feature = "runtime")]
+    #[test]
+    fn write_and_read() {
+        let files = vec![
+            ("/index.html".to_string(), b"index".to_vec()),
+            ("/build/app.js".to_string(), b"app".to_vec()),
+        ];
+        for name in ["a.zip", "a.tar", "a.tar.gz", "a.tgz"] {
+            let path =
+                std::env::temp_dir().join(format!("neutauri_{}_{}", std::process::id(), name));
+            write(&path, &files).unwrap();
+            assert!(is_archive(&path));
+            let read = read(&path);
+            fs::remove_file(&path).unwrap();
+            assert_eq!(
+                read.unwrap(),
+                [
+                    ("index.html".to_string(), b"index".to_vec()),
+                    ("build/app.js".to_string(), b"app".to_vec()),
+                ],
+                "{}",
+                name
+            );
+        }
+        let path = std::env::temp_dir().join("neutauri_archive.rar");
+        assert!(write(&path, &files).is_err());
+        assert!(!is_archive(&path));
+    }
+}
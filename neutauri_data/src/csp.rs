@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Csp {
+    Policy(String),
+    Directives(BTreeMap<String, Vec<String>>),
+}
+
+impl Csp {
+    pub fn policy(&self) -> String {
+        match self {
+            Self::Policy(policy) => policy.trim().to_string(),
+            Self::Directives(directives) => directives
+                .iter()
+                .map(|(name, values)| {
+                    let mut directive = name.to_ascii_lowercase();
+                    for value in values {
+                        directive.push(' ');
+                        directive.push_str(value);
+                    }
+                    directive
+                })
+                .collect::<Vec<_>>()
+                .join("; "),
+        }
+    }
+}
+
+// 将 nonce 加入 script-src 与 style-src，没有这两项时从 default-src 派生
+pub fn with_nonce(policy: &str, nonce: &str) -> String {
+    let mut directives: Vec<(String, Vec<String>)> = policy
+        .split(';')
+        .filter_map(|directive| {
+            let mut parts = directive.split_whitespace();
+            let name = parts.next()?.to_ascii_lowercase();
+            Some((name, parts.map(str::to_string).collect()))
+        })
+        .collect();
+    let default = directives
+        .iter()
+        .find(|(name, _)| name == "default-src")
+        .map(|(_, values)| values.clone());
+    for kind in ["script-src", "style-src"] {
+        let index = match directives.iter().position(|(name, _)| name == kind) {
+            Some(index) => index,
+            None => match &default {
+                Some(default) => {
+                    directives.push((kind.to_string(), default.clone()));
+                    directives.len() - 1
+                }
+                None => continue,
+            },
+        };
+        let values = &mut directives[index].1;
+        // 'unsafe-inline' 在存在 nonce 时会被忽略，此时不添加以免改变原有语义
+        if !values.iter().any(|v| v == "'unsafe-inline'") {
+            values.push(format!("'nonce-{}'", nonce));
+        }
+    }
+    directives
+        .iter()
+        .map(|(name, values)| {
+            let mut directive = name.clone();
+            for value in values {
+                directive.push(' ');
+                directive.push_str(value);
+            }
+            directive
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// 每次启动生成一次，由系统的安全随机数生成器提供 128 位随机数
+#[cfg(feature = "runtime")]
+pub fn nonce() -> std::io::Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// 渲染 CSP meta 标签，运行时按每次启动的 nonce 生成
+pub fn meta_tag(policy: &str) -> String {
+    format!(
+        "<meta http-equiv=\"Content-Security-Policy\" content=\"{}\">",
+        policy.replace('&', "&amp;").replace('"', "&quot;")
+    )
+}
+
+// 在 <head> 中插入标签，没有 <head> 时插入到文档开头（<!DOCTYPE> 之后）
+pub fn inject(html: &[u8], tags: &str) -> Vec<u8> {
+    let lower = html.to_ascii_lowercase();
+    // 返回标签结束后的位置，要求标签名后紧跟空白或 >，避免匹配到 <header>
+    let after = |tag: &[u8]| {
+        let start = lower.windows(tag.len() + 1).position(|w| {
+            w.starts_with(tag) && (w[tag.len()] == b'>' || w[tag.len()].is_ascii_whitespace())
+        })?;
+        let end = lower[start..].iter().position(|&b| b == b'>')?;
+        Some(start + end + 1)
+    };
+    let position = after(b"<head").or_else(|| after(b"<!doctype")).unwrap_or(0);
+    let mut output = Vec::with_capacity(html.len() + tags.len());
+    output.extend(&html[..position]);
+    output.extend(tags.as_bytes());
+    output.extend(&html[position..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy() {
+        assert_eq!(
+            Csp::Policy(" default-src 'self' ".into()).policy(),
+            "default-src 'self'"
+        );
+        let directives = [
+            ("default-src".to_string(), vec!["'self'".to_string()]),
+            (
+                "IMG-SRC".to_string(),
+                vec!["'self'".to_string(), "data:".to_string()],
+            ),
+        ];
+        let csp = Csp::Directives(directives.into_iter().collect());
+        // 按名称排序，名称转换为小写
+        assert_eq!(csp.policy(), "img-src 'self' data:; default-src 'self'");
+    }
+
+    #[test]
+    fn nonce_directives() {
+        assert_eq!(
+            with_nonce("default-src 'self'; img-src data:", "abc"),
+            "default-src 'self'; img-src data:; script-src 'self' 'nonce-abc'; style-src 'self' 'nonce-abc'"
+        );
+        assert_eq!(
+            with_nonce("script-src 'self'; style-src 'unsafe-inline'", "abc"),
+            "script-src 'self' 'nonce-abc'; style-src 'unsafe-inline'"
+        );
+        assert_eq!(with_nonce("img-src data:", "abc"), "img-src data:");
+    }
+
+    #[cfg(feature = "runtime")]
+    #[test]
+    fn random_nonce() {
+        let (a, b) = (nonce().unwrap(), nonce().unwrap());
+        assert_eq!(a.len(), 32);
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn head_tags() {
+        let meta = meta_tag("img-src \"x\" &");
+        assert_eq!(
+            meta,
+            "<meta http-equiv=\"Content-Security-Policy\" content=\"img-src &quot;x&quot; &amp;\">"
+        );
+        let inject = |html: &str| String::from_utf8(inject(html.as_bytes(), &meta)).unwrap();
+        assert_eq!(
+            inject("<html><header></header><HEAD lang=\"en\"><title></title></HEAD></html>"),
+            format!(
+                "<html><header></header><HEAD lang=\"en\">{}<title></title></HEAD></html>",
+                meta
+            )
+        );
+        assert_eq!(
+            inject("<!DOCTYPE html><p>a</p>"),
+            format!("<!DOCTYPE html>{}<p>a</p>", meta)
+        );
+        assert_eq!(inject("<p>a</p>"), format!("{}<p>a</p>", meta));
+    }
+}
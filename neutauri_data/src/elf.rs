@@ -0,0 +1,377 @@
+// 仅处理定位/追加节所需的最少 ELF 结构，不依赖外部解析库
+use std::io::{self, Result};
+#[cfg(feature = "runtime")]
+use std::{
+    fs,
+    io::{Read, Seek, SeekFrom},
+};
+
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+const SHN_XINDEX: u16 = 0xffff;
+#[cfg(feature = "bundler")]
+const SHN_LORESERVE: u16 = 0xff00;
+#[cfg(feature = "bundler")]
+const SHT_PROGBITS: u32 = 1;
+
+#[derive(Clone, Copy, Debug)]
+struct Header {
+    is_64: bool,
+    is_le: bool,
+    shoff: u64,
+    shentsize: u16,
+    shnum: u16,
+    shstrndx: u16,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Section {
+    name: u32,
+    offset: u64,
+    size: u64,
+}
+
+fn invalid<T>(msg: &str) -> Result<T> {
+    Err(io::Error::new(io::ErrorKind::InvalidData, msg.to_string()))
+}
+
+fn read_uint(buf: &[u8], offset: usize, len: usize, is_le: bool) -> Result<u64> {
+    let bytes = match buf.get(offset..offset + len) {
+        Some(bytes) => bytes,
+        None => return invalid("ELF structure out of bounds"),
+    };
+    let mut value = 0u64;
+    for i in 0..len {
+        let byte = if is_le { bytes[len - 1 - i] } else { bytes[i] };
+        value = (value << 8) | byte as u64;
+    }
+    Ok(value)
+}
+
+#[cfg(feature = "bundler")]
+fn write_uint(buf: &mut [u8], offset: usize, len: usize, is_le: bool, value: u64) {
+    for i in 0..len {
+        let byte = (value >> (8 * i)) as u8;
+        if is_le {
+            buf[offset + i] = byte;
+        } else {
+            buf[offset + len - 1 - i] = byte;
+        }
+    }
+}
+
+impl Header {
+    #[cfg(feature = "runtime")]
+    const LEN: usize = 64;
+
+    fn parse(buf: &[u8]) -> Result<Option<Self>> {
+        if buf.len() < 0x34 || &buf[..4] != ELF_MAGIC {
+            return Ok(None);
+        }
+        let is_64 = match buf[4] {
+            1 => false,
+            2 => true,
+            _ => return invalid("unknown ELF class"),
+        };
+        let is_le = match buf[5] {
+            1 => true,
+            2 => false,
+            _ => return invalid("unknown ELF data encoding"),
+        };
+        let header = if is_64 {
+            Self {
+                is_64,
+                is_le,
+                shoff: read_uint(buf, 0x28, 8, is_le)?,
+                shentsize: read_uint(buf, 0x3a, 2, is_le)? as u16,
+                shnum: read_uint(buf, 0x3c, 2, is_le)? as u16,
+                shstrndx: read_uint(buf, 0x3e, 2, is_le)? as u16,
+            }
+        } else {
+            Self {
+                is_64,
+                is_le,
+                shoff: read_uint(buf, 0x20, 4, is_le)?,
+                shentsize: read_uint(buf, 0x2e, 2, is_le)? as u16,
+                shnum: read_uint(buf, 0x30, 2, is_le)? as u16,
+                shstrndx: read_uint(buf, 0x32, 2, is_le)? as u16,
+            }
+        };
+        // 没有节头表的 ELF 文件是合法的，此时 shstrndx 无意义
+        if header.shnum != 0 && (header.shstrndx == SHN_XINDEX || header.shstrndx >= header.shnum) {
+            return invalid("unsupported ELF section header layout");
+        }
+        Ok(Some(header))
+    }
+
+    fn section_table_len(&self) -> usize {
+        self.shentsize as usize * self.shnum as usize
+    }
+
+    // 解析节头表中的第 index 项
+    fn section(&self, table: &[u8], index: usize) -> Result<Section> {
+        let base = index * self.shentsize as usize;
+        if self.is_64 {
+            Ok(Section {
+                name: read_uint(table, base, 4, self.is_le)? as u32,
+                offset: read_uint(table, base + 24, 8, self.is_le)?,
+                size: read_uint(table, base + 32, 8, self.is_le)?,
+            })
+        } else {
+            Ok(Section {
+                name: read_uint(table, base, 4, self.is_le)? as u32,
+                offset: read_uint(table, base + 16, 4, self.is_le)?,
+                size: read_uint(table, base + 20, 4, self.is_le)?,
+            })
+        }
+    }
+}
+
+fn section_name(strtab: &[u8], offset: u32) -> &[u8] {
+    let name = strtab.get(offset as usize..).unwrap_or_default();
+    match name.iter().position(|&b| b == 0) {
+        Some(end) => &name[..end],
+        None => name,
+    }
+}
+
+fn find_in(header: &Header, table: &[u8], strtab: &[u8], name: &str) -> Result<Option<Section>> {
+    for index in 0..header.shnum as usize {
+        let section = header.section(table, index)?;
+        if section_name(strtab, section.name) == name.as_bytes() {
+            return Ok(Some(section));
+        }
+    }
+    Ok(None)
+}
+
+// 在 ELF 文件中查找指定名称的节，返回 (偏移, 长度)；非 ELF 文件或没有该节时返回 None，
+// ELF 结构损坏时返回错误
+#[cfg(feature = "runtime")]
+pub(crate) fn find_section(file: &mut fs::File, name: &str) -> Result<Option<(u64, u64)>> {
+    let file_len = file.metadata()?.len();
+    let mut buf = Vec::with_capacity(Header::LEN);
+    file.seek(SeekFrom::Start(0))?;
+    file.by_ref()
+        .take(Header::LEN as u64)
+        .read_to_end(&mut buf)?;
+    let header = match Header::parse(&buf)? {
+        Some(header) if header.shnum > 0 => header,
+        _ => return Ok(None),
+    };
+    let mut table = vec![0; header.section_table_len()];
+    read_at(file, file_len, header.shoff, &mut table)?;
+    let strtab_section = header.section(&table, header.shstrndx as usize)?;
+    if strtab_section.size > file_len {
+        return invalid("ELF string table out of bounds");
+    }
+    let mut strtab = vec![0; strtab_section.size as usize];
+    read_at(file, file_len, strtab_section.offset, &mut strtab)?;
+    match find_in(&header, &table, &strtab, name)? {
+        Some(section) if section.offset.saturating_add(section.size) > file_len => {
+            invalid("ELF section out of bounds")
+        }
+        section => Ok(section.map(|s| (s.offset, s.size))),
+    }
+}
+
+#[cfg(feature = "runtime")]
+fn read_at(file: &mut fs::File, file_len: u64, offset: u64, buf: &mut [u8]) -> Result<()> {
+    if offset.saturating_add(buf.len() as u64) > file_len {
+        return invalid("ELF structure out of bounds");
+    }
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buf)
+}
+
+// 将 payload 作为新节写入 ELF 文件，原有内容保持不变：
+// 数据、新的节名字符串表和新的节头表依次追加到文件末尾，再更新 ELF 头指向新的节头表
+#[cfg(feature = "bundler")]
+pub(crate) fn add_section(elf: &[u8], name: &str, payload: &[u8]) -> Result<Vec<u8>> {
+    let header = match Header::parse(elf)? {
+        Some(header) => header,
+        None => return invalid("not an ELF file"),
+    };
+    if header.shnum == 0 {
+        return invalid("ELF file has no section header table");
+    }
+    if header.shnum >= SHN_LORESERVE - 1 {
+        return invalid("too many ELF sections");
+    }
+    let table_start = header.shoff as usize;
+    let mut table =
+        match elf.get(table_start..table_start.saturating_add(header.section_table_len())) {
+            Some(table) => table.to_vec(),
+            None => return invalid("ELF section header table out of bounds"),
+        };
+    let strtab_section = header.section(&table, header.shstrndx as usize)?;
+    let strtab_start = strtab_section.offset as usize;
+    let strtab_end = strtab_start.saturating_add(strtab_section.size as usize);
+    let mut strtab = match elf.get(strtab_start..strtab_end) {
+        Some(strtab) => strtab.to_vec(),
+        None => return invalid("ELF string table out of bounds"),
+    };
+    if find_in(&header, &table, &strtab, name)?.is_some() {
+        return invalid("ELF section already exists");
+    }
+    let (word, align) = if header.is_64 { (8, 8) } else { (4, 4) };
+    let is_le = header.is_le;
+
+    let mut target = elf.to_vec();
+    let pad = |target: &mut Vec<u8>| target.resize(target.len().div_ceil(align) * align, 0);
+    // 写入数据
+    pad(&mut target);
+    let payload_offset = target.len();
+    target.extend(payload);
+    // 写入新的节名字符串表
+    let name_offset = strtab.len();
+    strtab.extend(name.as_bytes());
+    strtab.push(0);
+    let strtab_offset = target.len();
+    target.extend(&strtab);
+    // 更新字符串表节头
+    let strtab_header = header.shstrndx as usize * header.shentsize as usize;
+    let (offset_field, size_field) = if header.is_64 { (24, 32) } else { (16, 20) };
+    write_uint(
+        &mut table,
+        strtab_header + offset_field,
+        word,
+        is_le,
+        strtab_offset as u64,
+    );
+    write_uint(
+        &mut table,
+        strtab_header + size_field,
+        word,
+        is_le,
+        strtab.len() as u64,
+    );
+    // 追加新的节头
+    let mut section = vec![0; header.shentsize as usize];
+    write_uint(&mut section, 0, 4, is_le, name_offset as u64);
+    write_uint(&mut section, 4, 4, is_le, SHT_PROGBITS as u64);
+    write_uint(
+        &mut section,
+        offset_field,
+        word,
+        is_le,
+        payload_offset as u64,
+    );
+    write_uint(&mut section, size_field, word, is_le, payload.len() as u64);
+    let addralign_field = if header.is_64 { 48 } else { 32 };
+    write_uint(&mut section, addralign_field, word, is_le, 1);
+    table.extend(section);
+    // 写入新的节头表
+    pad(&mut target);
+    let table_offset = target.len();
+    target.extend(&table);
+    // 更新 ELF 头
+    if header.is_64 {
+        write_uint(&mut target, 0x28, 8, is_le, table_offset as u64);
+        write_uint(&mut target, 0x3c, 2, is_le, header.shnum as u64 + 1);
+    } else {
+        write_uint(&mut target, 0x20, 4, is_le, table_offset as u64);
+        write_uint(&mut target, 0x30, 2, is_le, header.shnum as u64 + 1);
+    }
+    Ok(target)
+}
+
+#[cfg(all(test, feature = "bundler"))]
+pub(crate) mod tests {
+    use super::*;
+
+    // 只有空节和节名字符串表的最小 ELF 文件
+    pub(crate) fn minimal_elf(is_64: bool, is_le: bool) -> Vec<u8> {
+        let strtab = b"\0.shstrtab\0";
+        let (header_len, entry_len) = if is_64 { (64, 64) } else { (52, 40) };
+        let table_offset = (header_len + strtab.len()).div_ceil(8) * 8;
+        let mut elf = vec![0; table_offset + entry_len * 2];
+        elf[..4].copy_from_slice(ELF_MAGIC);
+        elf[4] = if is_64 { 2 } else { 1 };
+        elf[5] = if is_le { 1 } else { 2 };
+        elf[6] = 1;
+        elf[header_len..header_len + strtab.len()].copy_from_slice(strtab);
+        let strtab_entry = table_offset + entry_len;
+        if is_64 {
+            write_uint(&mut elf, 0x28, 8, is_le, table_offset as u64);
+            write_uint(&mut elf, 0x3a, 2, is_le, entry_len as u64);
+            write_uint(&mut elf, 0x3c, 2, is_le, 2);
+            write_uint(&mut elf, 0x3e, 2, is_le, 1);
+            write_uint(&mut elf, strtab_entry, 4, is_le, 1);
+            write_uint(&mut elf, strtab_entry + 4, 4, is_le, 3);
+            write_uint(&mut elf, strtab_entry + 24, 8, is_le, header_len as u64);
+            write_uint(&mut elf, strtab_entry + 32, 8, is_le, strtab.len() as u64);
+        } else {
+            write_uint(&mut elf, 0x20, 4, is_le, table_offset as u64);
+            write_uint(&mut elf, 0x2e, 2, is_le, entry_len as u64);
+            write_uint(&mut elf, 0x30, 2, is_le, 2);
+            write_uint(&mut elf, 0x32, 2, is_le, 1);
+            write_uint(&mut elf, strtab_entry, 4, is_le, 1);
+            write_uint(&mut elf, strtab_entry + 4, 4, is_le, 3);
+            write_uint(&mut elf, strtab_entry + 16, 4, is_le, header_len as u64);
+            write_uint(&mut elf, strtab_entry + 20, 4, is_le, strtab.len() as u64);
+        }
+        elf
+    }
+
+    fn find(elf: &[u8], name: &str) -> Result<Option<Section>> {
+        let header = Header::parse(elf)?.unwrap();
+        let start = header.shoff as usize;
+        let table = &elf[start..start + header.section_table_len()];
+        let strtab_section = header.section(table, header.shstrndx as usize)?;
+        let strtab_start = strtab_section.offset as usize;
+        let strtab = &elf[strtab_start..strtab_start + strtab_section.size as usize];
+        find_in(&header, table, strtab, name)
+    }
+
+    #[test]
+    fn add_and_find_section() {
+        for (is_64, is_le) in [(true, true), (true, false), (false, true), (false, false)] {
+            let elf = minimal_elf(is_64, is_le);
+            let target = add_section(&elf, ".neutauri", b"payload").unwrap();
+            // ELF 头之后的原有内容保持不变
+            let header_len = if is_64 { 64 } else { 52 };
+            assert_eq!(&target[header_len..elf.len()], &elf[header_len..]);
+            let section = find(&target, ".neutauri").unwrap().unwrap();
+            let start = section.offset as usize;
+            assert_eq!(&target[start..start + section.size as usize], b"payload");
+            assert!(find(&target, ".shstrtab").unwrap().is_some());
+            assert!(find(&target, ".missing").unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn reject_invalid_elf() {
+        assert!(add_section(
+            b"not an elf file at all, just some text....................",
+            ".neutauri",
+            b""
+        )
+        .is_err());
+        let elf = add_section(&minimal_elf(true, true), ".neutauri", b"payload").unwrap();
+        assert!(add_section(&elf, ".neutauri", b"payload").is_err());
+        let mut truncated = minimal_elf(true, true);
+        truncated.truncate(truncated.len() - 1);
+        assert!(add_section(&truncated, ".neutauri", b"payload").is_err());
+        let mut class = minimal_elf(true, true);
+        class[4] = 3;
+        assert!(Header::parse(&class).is_err());
+    }
+
+    #[cfg(feature = "runtime")]
+    #[test]
+    fn find_section_in_file() {
+        let path = std::env::temp_dir().join(format!("neutauri_elf_{}", std::process::id()));
+        let elf = add_section(&minimal_elf(true, true), ".neutauri", b"payload").unwrap();
+        fs::write(&path, &elf).unwrap();
+        let found = find_section(&mut fs::File::open(&path).unwrap(), ".neutauri").unwrap();
+        let (offset, size) = found.unwrap();
+        assert_eq!(&elf[offset as usize..(offset + size) as usize], b"payload");
+        // 节头表被截断时返回错误，而不是当作没有该节
+        fs::write(&path, &elf[..elf.len() - 8]).unwrap();
+        assert!(find_section(&mut fs::File::open(&path).unwrap(), ".neutauri").is_err());
+        fs::write(&path, b"plain file").unwrap();
+        let found = find_section(&mut fs::File::open(&path).unwrap(), ".neutauri").unwrap();
+        assert!(found.is_none());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,352 @@
+use crate::{
+    glob_matches_any, glob_patterns, is_html, reference, route, Fingerprint, Source, WebViewAttr,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Result,
+};
+
+// 未配置 include 时默认处理的扩展名，html 作为入口始终保留原名；
+// js 中的引用不会被改写，所以 js 与只能由 js 加载的 wasm 不在默认范围内
+const EXTENSIONS: &[&str] = &[
+    "css", "png", "jpg", "jpeg", "gif", "webp", "avif", "svg", "ico", "woff", "woff2", "ttf",
+    "otf", "eot",
+];
+
+pub(crate) struct Fingerprinter {
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+}
+
+impl Fingerprinter {
+    pub(crate) fn new(config: &Fingerprint) -> Result<Option<Self>> {
+        if !config.enable {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            include: glob_patterns(&config.include)?,
+            exclude: glob_patterns(&config.exclude)?,
+        }))
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        if is_html(path) || glob_matches_any(&self.exclude, path) {
+            return false;
+        }
+        if !self.include.is_empty() {
+            return glob_matches_any(&self.include, path);
+        }
+        let name = path.rsplit('/').next().unwrap_or(path);
+        match name.rsplit_once('.') {
+            Some((_, extension)) => EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()),
+            None => false,
+        }
+    }
+
+    // 未配置 include 时只处理被 html/css 引用、且文件名没有出现在任何 js 中的文件
+    fn candidates(&self, sources: &[Source]) -> BTreeSet<usize> {
+        let mut candidates = (0..sources.len())
+            .filter(|&i| self.matches(&sources[i].path))
+            .collect::<BTreeSet<_>>();
+        if !self.include.is_empty() {
+            return candidates;
+        }
+        let markup = sources
+            .iter()
+            .filter(|source| is_html(&source.path) || is_css(&source.path));
+        let referenced = markup
+            .flat_map(|source| reference::find(&source.path, &source.data))
+            .map(|(_, target)| target)
+            .collect::<BTreeSet<_>>();
+        let scripts = sources
+            .iter()
+            .filter(|source| is_script(&source.path))
+            .map(|source| String::from_utf8_lossy(&source.data))
+            .collect::<Vec<_>>();
+        candidates.retain(|&i| {
+            let path = &sources[i].path;
+            let name = path.rsplit('/').next().unwrap_or(path);
+            referenced.contains(path) && !scripts.iter().any(|script| script.contains(name))
+        });
+        candidates
+    }
+
+    // 重命名匹配的文件并改写 html/css 中的引用，返回排序后的新路径
+    pub(crate) fn apply(&self, sources: &mut [Source], webview_attr: &WebViewAttr) -> Vec<String> {
+        let candidates = self.candidates(sources);
+        let mut renamed = BTreeMap::new();
+        let (mut styles, others): (Vec<usize>, Vec<usize>) = candidates
+            .into_iter()
+            .partition(|&i| is_css(&sources[i].path));
+        let rename = |renamed: &mut BTreeMap<String, String>, source: &Source| {
+            let hashed = hashed_path(source);
+            if !named_in_config(webview_attr, &source.path, &hashed) {
+                renamed.insert(source.path.clone(), hashed);
+            }
+        };
+        for i in others {
+            rename(&mut renamed, &sources[i]);
+        }
+        // css 改写引用后内容才确定，互相 @import 时被引用的文件需要先确定名称
+        while !styles.is_empty() {
+            let pending = styles
+                .iter()
+                .map(|&i| sources[i].path.clone())
+                .collect::<BTreeSet<_>>();
+            let mut ready = styles
+                .iter()
+                .copied()
+                .filter(|&i| {
+                    reference::find(&sources[i].path, &sources[i].data)
+                        .into_iter()
+                        .all(|(_, target)| target == sources[i].path || !pending.contains(&target))
+                })
+                .collect::<Vec<_>>();
+            // 存在循环引用时不再等待
+            if ready.is_empty() {
+                ready = styles.clone();
+            }
+            for &i in &ready {
+                rewrite(&mut sources[i], &renamed);
+                rename(&mut renamed, &sources[i]);
+            }
+            styles.retain(|i| !ready.contains(i));
+        }
+        for source in sources.iter_mut() {
+            match renamed.get(&source.path) {
+                Some(path) => source.path = path.clone(),
+                None if is_html(&source.path) || is_css(&source.path) => rewrite(source, &renamed),
+                None => (),
+            }
+        }
+        let mut paths = renamed.into_values().collect::<Vec<_>>();
+        paths.sort();
+        paths
+    }
+}
+
+fn is_css(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".css")
+}
+
+fn is_script(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.ends_with(".js") || path.ends_with(".mjs")
+}
+
+// 入口、错误页面、路由目标以及按路径匹配的响应头规则都使用原路径，这些文件保留原名
+fn named_in_config(webview_attr: &WebViewAttr, path: &str, hashed: &str) -> bool {
+    let pages = [
+        &webview_attr.url,
+        &webview_attr.not_found,
+        &webview_attr.error_page,
+    ];
+    let page = pages.into_iter().flatten().any(|page| {
+        let end = page.find(['?', '#']).unwrap_or(page.len());
+        page[..end].trim_start_matches('/') == path.trim_start_matches('/')
+    });
+    let routed = webview_attr.routes.iter().any(|route| {
+        let end = route.to.find(['?', '#']).unwrap_or(route.to.len());
+        route::wildcard_match(&route.to[..end], path).is_some()
+    });
+    page || routed || webview_attr.headers_for(path) != webview_attr.headers_for(hashed)
+}
+
+// 在扩展名前插入内容的 SHA-256 前 16 位十六进制，如 "/build/bundle.js" -> "/build/bundle.3f2a1c9e5b7d0a64.js"，
+// 使用固定算法保证不同版本的打包工具对相同内容生成相同的文件名
+fn hashed_path(source: &Source) -> String {
+    let digest = Sha256::digest(&source.data);
+    let hash = digest[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    let (dir, name) = source.path.rsplit_once('/').unwrap_or(("", &source.path));
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => {
+            format!("{}/{}.{}.{}", dir, stem, hash, extension)
+        }
+        _ => format!("{}/{}.{}", dir, name, hash),
+    }
+}
+
+// 只替换引用中的文件名部分，保留原有的相对路径、查询参数与锚点
+fn rewrite(source: &mut Source, renamed: &BTreeMap<String, String>) {
+    let references = reference::find(&source.path, &source.data);
+    let text = match std::str::from_utf8(&source.data) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    let mut output = String::with_capacity(text.len());
+    let mut last = 0;
+    for (range, target) in references {
+        let name = match renamed.get(&target) {
+            Some(path) => path.rsplit('/').next().unwrap_or(path),
+            None => continue,
+        };
+        if range.start < last {
+            continue;
+        }
+        let reference = &text[range.clone()];
+        let end = reference.find(['?', '#']).unwrap_or(reference.len());
+        let start = reference[..end].rfind('/').map_or(0, |i| i + 1);
+        output.push_str(&text[last..range.start]);
+        output.push_str(&reference[..start]);
+        output.push_str(name);
+        output.push_str(&reference[end..]);
+        last = range.end;
+    }
+    if last > 0 {
+        output.push_str(&text[last..]);
+        source.data = output.into_bytes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, HeaderRule, Route};
+
+    fn source(path: &str, data: &str) -> Source {
+        Source {
+            path: path.to_string(),
+            data: data.as_bytes().to_vec(),
+            size: data.len() as u64,
+            pipeline: 0,
+        }
+    }
+
+    fn apply(config: &Config, sources: &mut [Source]) -> Vec<String> {
+        let mut fingerprint = config.fingerprint.clone();
+        fingerprint.enable = true;
+        let fingerprinter = Fingerprinter::new(&fingerprint).unwrap().unwrap();
+        fingerprinter.apply(sources, &config.webview_attr().unwrap())
+    }
+
+    fn text(sources: &[Source], i: usize) -> &str {
+        std::str::from_utf8(&sources[i].data).unwrap()
+    }
+
+    #[test]
+    fn hashed_paths() {
+        let path = hashed_path(&source("/build/bundle.js", "a"));
+        assert!(path.starts_with("/build/bundle.") && path.ends_with(".js"));
+        // "a" 的 SHA-256 为 ca978112ca1bbdca...
+        assert_eq!(path, "/build/bundle.ca978112ca1bbdca.js");
+        assert_ne!(path, hashed_path(&source("/build/bundle.js", "b")));
+        assert!(hashed_path(&source("/.env", "a")).starts_with("/.env."));
+    }
+
+    // html 的 src/href/srcset 与 css 的 url()/@import 都会被改写，保留相对路径与查询参数
+    #[test]
+    fn markup_references() {
+        let mut sources = [
+            source(
+                "/index.html",
+                r#"<link href="css/app.css"><img src='logo.png?v=1' srcset="logo.png 1x, /img/big.png 2x"><a href=/img/big.png#top>x</a>"#,
+            ),
+            source(
+                "/css/app.css",
+                "@import './base.css'; a { background: url(../logo.png) }",
+            ),
+            source("/css/base.css", "b { background: url(\"/img/big.png\") }"),
+            source("/logo.png", "logo"),
+            source("/img/big.png", "big"),
+            source("/unused.png", "unused"),
+        ];
+        let renamed = apply(&Config::default(), &mut sources);
+        let name = |i: usize| sources[i].path.rsplit('/').next().unwrap().to_string();
+        let (app, base, logo, big) = (name(1), name(2), name(3), name(4));
+        assert_eq!(renamed.len(), 4);
+        assert_eq!(
+            text(&sources, 0),
+            format!(
+                r#"<link href="css/{app}"><img src='{logo}?v=1' srcset="{logo} 1x, /img/{big} 2x"><a href=/img/{big}#top>x</a>"#
+            )
+        );
+        assert_eq!(
+            text(&sources, 1),
+            format!("@import './{base}'; a {{ background: url(../{logo}) }}")
+        );
+        assert_eq!(
+            text(&sources, 2),
+            format!("b {{ background: url(\"/img/{big}\") }}")
+        );
+        // 没有被引用的文件保留原名
+        assert_eq!(sources[5].path, "/unused.png");
+        assert_eq!(sources[0].path, "/index.html");
+    }
+
+    // js 中的引用不会被改写，默认情况下 js 与在 js 中出现的文件保留原名
+    #[test]
+    fn script_references() {
+        let html = r#"<script src="app.js"></script><img src="a.png"><img src="b.png"><script>var src = "a.png";</script>"#;
+        let mut sources = [
+            source("/index.html", html),
+            source(
+                "/app.js",
+                "import './util.js'; fetch('/b.png'); new Worker('worker.js')",
+            ),
+            source("/util.js", ""),
+            source("/a.png", "a"),
+            source("/b.png", "b"),
+        ];
+        let renamed = apply(&Config::default(), &mut sources);
+        assert_eq!(renamed.len(), 1);
+        assert!(renamed[0].starts_with("/a."));
+        assert_eq!(sources[1].path, "/app.js");
+        assert_eq!(sources[2].path, "/util.js");
+        assert_eq!(sources[4].path, "/b.png");
+        // 内联脚本中的同名变量不是引用
+        let a = sources[3].path.trim_start_matches('/');
+        assert_eq!(
+            text(&sources, 0),
+            html.replacen(r#"src="a.png""#, &format!(r#"src="{}""#, a), 1)
+        );
+    }
+
+    // 配置中按原路径指定的文件保留原名
+    #[test]
+    fn config_paths() {
+        let config = Config {
+            not_found: Some("404.css".into()),
+            routes: vec![Route {
+                from: "/icons/*".into(),
+                to: "/assets/icons/*".into(),
+                status: None,
+            }],
+            headers: vec![
+                HeaderRule {
+                    path: "fonts/a.woff2".into(),
+                    mime: None,
+                    headers: [("X-Font".to_string(), "a".to_string())].into(),
+                },
+                HeaderRule {
+                    path: "**/*.woff2".into(),
+                    mime: None,
+                    headers: [("Access-Control-Allow-Origin".to_string(), "*".to_string())].into(),
+                },
+            ],
+            fingerprint: Fingerprint {
+                include: vec!["**/*.css".into(), "**/*.png".into(), "**/*.woff2".into()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut sources = [
+            source("/404.css", "a"),
+            source("/assets/icons/x.png", "x"),
+            source("/fonts/a.woff2", "a"),
+            source("/fonts/b.woff2", "b"),
+            source("/y.png", "y"),
+        ];
+        let renamed = apply(&config, &mut sources);
+        assert_eq!(sources[0].path, "/404.css");
+        assert_eq!(sources[1].path, "/assets/icons/x.png");
+        assert_eq!(sources[2].path, "/fonts/a.woff2");
+        // **/*.woff2 同样匹配带哈希的文件名，不影响重命名
+        assert_eq!(renamed.len(), 2);
+        assert!(sources[3].path.starts_with("/fonts/b."));
+        assert!(sources[4].path.starts_with("/y."));
+    }
+}
@@ -1,23 +1,198 @@
 use bincode::Options;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     fs,
     io::{self, Read, Result},
     path::{self, Component, Path, PathBuf},
+    sync::OnceLock,
 };
 use wry::application::dpi::Position;
 
-const MAGIC_NUMBER_START: &[u8; 9] = b"NEUTFSv01";
+#[cfg(feature = "bundler")]
+pub use archive::is_archive;
+#[cfg(feature = "runtime")]
+pub use csp::nonce as csp_nonce;
+pub use csp::{inject as csp_inject, meta_tag as csp_meta_tag, with_nonce as csp_with_nonce, Csp};
+pub use route::{Resolution, Route};
+
+// 数据结构改变时需要更新版本号，运行时只读取同一版本的数据
+const MAGIC_NUMBER_START: &[u8; 9] = b"NEUTFSv02";
 const MAGIC_NUMBER_END: &[u8; 9] = b"NEUTFSEnd";
 const USIZE_LEN: usize = usize::MAX.to_be_bytes().len();
+pub const SECTION_NAME: &str = ".neutauri";
+// 页面可以通过 window.neutauri.window 调用的窗口操作，"*" 表示全部
+pub const WINDOW_API: &[&str] = &[
+    "minimize",
+    "maximize",
+    "restore",
+    "show",
+    "hide",
+    "focus",
+    "center",
+    "set_title",
+    "set_fullscreen",
+    "set_always_on_top",
+    "set_size",
+    "set_position",
+    "state",
+];
+
+#[cfg(feature = "bundler")]
+mod archive;
+mod csp;
+mod elf;
+#[cfg(feature = "bundler")]
+mod fingerprint;
+#[cfg(feature = "bundler")]
+mod minify;
+#[cfg(feature = "bundler")]
+mod reference;
+mod route;
+#[cfg(feature = "bundler")]
+mod template;
 
 #[non_exhaustive]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum Compress {
     Brotli,
     None,
 }
 
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionPreset {
+    Fast,
+    Default,
+    Max,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Compression {
+    pub preset: Option<CompressionPreset>,
+    pub codec: Option<Compress>,
+    pub level: Option<u32>,
+    pub window: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Embed {
+    #[default]
+    Trailer,
+    Section,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Payload {
+    pub version: String,
+    pub embed: Embed,
+    pub file_size: u64,
+    pub offset: u64,
+    pub size: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Entry {
+    pub path: String,
+    pub mime: String,
+    pub compress: Compress,
+    pub size: u64,
+    pub compressed_size: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BuildStats {
+    pub files: u64,
+    pub original_size: u64,
+    pub compressed_size: u64,
+    pub entries: Vec<Entry>,
+    pub broken_references: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Minify {
+    pub enable: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+// 点击关闭按钮时的行为：通知页面后退出、隐藏窗口，或由页面决定是否退出；
+// 隐藏后页面无法再显示窗口，Hide 只能在嵌入时配合 on_window_event 回调（如托盘图标）使用，
+// 打包成可执行文件时会被拒绝
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseBehavior {
+    #[default]
+    Exit,
+    Hide,
+    AskJs,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckReferences {
+    Off,
+    #[default]
+    Warn,
+    Error,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ServeMode {
+    #[default]
+    Protocol,
+    Localhost,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct Protocol {
+    pub scheme: String,
+    pub host: String,
+}
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Self {
+            scheme: "neu".into(),
+            host: "localhost".into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Fingerprint {
+    pub enable: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Template {
+    pub include: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum ByteSize {
+    Bytes(u64),
+    Text(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Budgets {
+    pub total: Option<ByteSize>,
+    pub files: BTreeMap<String, ByteSize>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct File {
     mime: String,
@@ -25,7 +200,7 @@ pub struct File {
     compress: Compress,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 struct Dir {
     files: Vec<(String, File)>,
     dirs: Vec<(String, Dir)>,
@@ -62,12 +237,52 @@ pub struct Config {
     pub transparent: bool,
     pub decorations: bool,
     pub always_on_top: bool,
+    // 无边框窗口中，距边缘多少逻辑像素内可以拖动调整大小，0 表示不启用
+    #[serde(default)]
+    pub resize_border: u32,
+    #[serde(default)]
+    pub close_behavior: CloseBehavior,
     pub icon: Option<PathBuf>,
     pub spa: bool,
     pub url: Option<String>,
     pub html: Option<PathBuf>,
     pub initialization_script: Option<PathBuf>,
     pub manifest: Option<PathBuf>,
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    #[serde(default)]
+    pub mounts: Vec<Mount>,
+    #[serde(default)]
+    pub embed: Embed,
+    #[serde(default)]
+    pub compression: Compression,
+    pub not_found: Option<String>,
+    pub error_page: Option<String>,
+    #[serde(default)]
+    pub protocol: Protocol,
+    #[serde(default)]
+    pub serve_mode: ServeMode,
+    #[serde(default)]
+    pub window_api: Vec<String>,
+    #[serde(default)]
+    pub routes: Vec<Route>,
+    #[serde(default)]
+    pub headers: Vec<HeaderRule>,
+    pub csp: Option<Csp>,
+    #[serde(default)]
+    pub csp_meta: bool,
+    #[serde(default)]
+    pub minify: Minify,
+    #[serde(default)]
+    pub fingerprint: Fingerprint,
+    #[serde(default)]
+    pub template: Template,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub check_references: CheckReferences,
+    #[serde(default)]
+    pub budgets: Budgets,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -91,6 +306,8 @@ pub struct WindowAttr {
     pub transparent: bool,
     pub decorations: bool,
     pub always_on_top: bool,
+    pub resize_border: u32,
+    pub close_behavior: CloseBehavior,
     pub icon: Option<Icon>,
 }
 
@@ -102,15 +319,50 @@ pub struct WebViewAttr {
     pub url: Option<String>,
     pub html: Option<String>,
     pub initialization_script: Option<String>,
+    pub headers: Vec<HeaderRule>,
+    pub csp: Option<String>,
+    pub csp_meta: bool,
+    pub routes: Vec<Route>,
+    pub not_found: Option<String>,
+    pub error_page: Option<String>,
+    pub protocol: Protocol,
+    pub serve_mode: ServeMode,
+    pub immutable: Vec<String>,
+    pub window_api: Vec<String>,
+    // 首次匹配响应头规则时编译，打包时已经校验过模式
+    #[serde(skip)]
+    header_patterns: OnceLock<Vec<Option<glob::Pattern>>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Mount {
+    pub prefix: String,
+    pub source: PathBuf,
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    pub compression: Option<Compression>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HeaderRule {
+    pub path: String,
+    pub mime: Option<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
 }
 
 #[cfg(feature = "runtime")]
 impl File {
     pub fn decompressed_data(&mut self) -> Result<Vec<u8>> {
-        let mut data = Vec::with_capacity(self.data.len());
-        let mut r = brotli::Decompressor::new(self.data.as_slice(), 4096);
-        r.read_to_end(&mut data)?;
-        Ok(data)
+        match self.compress {
+            Compress::Brotli => {
+                let mut data = Vec::with_capacity(self.data.len());
+                let mut r = brotli::Decompressor::new(self.data.as_slice(), 4096);
+                r.read_to_end(&mut data)?;
+                Ok(data)
+            }
+            Compress::None => Ok(self.data.clone()),
+        }
     }
     pub fn mimetype(&self) -> String {
         self.mime.clone()
@@ -120,38 +372,86 @@ impl File {
 #[cfg(feature = "runtime")]
 impl Data {
     pub fn new<P: AsRef<path::Path> + Copy>(path: P) -> Result<Self> {
-        use std::io::{Seek, SeekFrom};
+        Ok(Self::inspect(path)?.0)
+    }
+
+    pub fn inspect<P: AsRef<path::Path> + Copy>(path: P) -> Result<(Self, Payload)> {
         let mut base = fs::File::open(path)?;
+        let (embed, offset, size) = Self::locate(&mut base)?;
+        let (data, version) = Self::read_payload(&mut base, offset)?;
+        let payload = Payload {
+            version,
+            embed,
+            file_size: base.metadata()?.len(),
+            offset,
+            size,
+        };
+        Ok((data, payload))
+    }
+
+    // 优先从 ELF 节中查找数据，找不到时回退到文件末尾的 trailer，返回 (布局, 偏移, 长度)
+    fn locate(base: &mut fs::File) -> Result<(Embed, u64, u64)> {
+        use std::io::{Seek, SeekFrom};
+        // 不是 ELF 文件（如 PE 或独立的 .neu 文件）或没有数据节时回退，ELF 结构损坏时报错
+        let section = elf::find_section(base, SECTION_NAME)
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read ELF section: {}", e)))?;
+        if let Some((offset, size)) = section {
+            return Ok((Embed::Section, offset, size));
+        }
         let base_length = base.metadata()?.len();
-        let mut magic_number_start_data = [0; MAGIC_NUMBER_START.len()];
         let mut data_length_data = [0; USIZE_LEN];
-        let mut data = Vec::new();
         let mut magic_number_end_data = [0; MAGIC_NUMBER_END.len()];
+        // 没有数据时返回 NotFound，调用方可以据此回退到独立的 .neu 文件
+        let not_found = || io::Error::new(io::ErrorKind::NotFound, "no bundle data found");
+        if base_length < (MAGIC_NUMBER_END.len() + USIZE_LEN) as u64 {
+            return Err(not_found());
+        }
         base.seek(SeekFrom::Start(base_length - MAGIC_NUMBER_END.len() as u64))?;
         // 此时指针指向 MAGIC_NUMBER_END 之前
         base.read_exact(&mut magic_number_end_data)?;
         if &magic_number_end_data != MAGIC_NUMBER_END {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "MAGIC_NUMBER_END not found",
-            ));
+            return Err(not_found());
         }
         base.seek(SeekFrom::Start(
             base_length - MAGIC_NUMBER_END.len() as u64 - USIZE_LEN as u64,
         ))?;
         // 此时指针指向 data_length 之前
         base.read_exact(&mut data_length_data)?;
-        base.seek(SeekFrom::Start(
-            base_length - u64::from_be_bytes(data_length_data),
-        ))?;
+        let length = u64::from_be_bytes(data_length_data);
+        if length > base_length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid data length",
+            ));
+        }
+        Ok((Embed::Trailer, base_length - length, length))
+    }
+
+    // 返回数据与文件中记录的格式版本，如 "v02"
+    fn read_payload(base: &mut fs::File, start: u64) -> Result<(Self, String)> {
+        use std::io::{Seek, SeekFrom};
+        let mut magic_number_start_data = [0; MAGIC_NUMBER_START.len()];
+        let mut data_length_data = [0; USIZE_LEN];
+        let mut data = Vec::new();
+        base.seek(SeekFrom::Start(start))?;
         // 此时指针指向 MAGIC_NUMBER_START
         base.read_exact(&mut magic_number_start_data)?;
-        if &magic_number_start_data != MAGIC_NUMBER_START {
+        if magic_number_start_data[..6] != MAGIC_NUMBER_START[..6] {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
                 "MAGIC_NUMBER_START not found",
             ));
         }
+        if &magic_number_start_data != MAGIC_NUMBER_START {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "bundle format {} is not supported, expected {}; rebuild it with a matching neutauri_bundler",
+                    String::from_utf8_lossy(&magic_number_start_data[6..]),
+                    String::from_utf8_lossy(&MAGIC_NUMBER_START[6..])
+                ),
+            ));
+        }
         base.read_exact(&mut data_length_data)?;
         // 此时指针指向 Data 前
         base.take(u64::from_be_bytes(data_length_data))
@@ -166,7 +466,8 @@ impl Data {
                 return Err(io::Error::new(io::ErrorKind::InvalidData, e));
             }
         };
-        Ok(fs)
+        let version = String::from_utf8_lossy(&magic_number_start_data[6..]).to_string();
+        Ok((fs, version))
     }
 
     fn open_file(current_dir: &Dir, mut path: path::Iter) -> Result<File> {
@@ -198,88 +499,292 @@ impl Data {
         };
         Self::open_file(&self.fs, path.iter())
     }
+
+    // 返回所有文件解压后的内容，路径形如 "/build/bundle.js"
+    pub fn files(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        fn walk(dir: &Dir, prefix: &str, files: &mut Vec<(String, Vec<u8>)>) -> Result<()> {
+            for (name, file) in &dir.files {
+                files.push((
+                    format!("{}/{}", prefix, name),
+                    file.clone().decompressed_data()?,
+                ));
+            }
+            for (name, dir) in &dir.dirs {
+                walk(dir, &format!("{}/{}", prefix, name), files)?;
+            }
+            Ok(())
+        }
+        let mut files = Vec::new();
+        walk(&self.fs, "", &mut files)?;
+        Ok(files)
+    }
+
+    // 将文件系统导出为 zip/tar 归档，格式由扩展名决定，返回导出的文件数
+    #[cfg(feature = "bundler")]
+    pub fn export<P: AsRef<path::Path>>(&self, target: P) -> Result<usize> {
+        let files = self.files()?;
+        archive::write(target.as_ref(), &files)?;
+        Ok(files.len())
+    }
+
+    // 列出所有文件，解压以获取原始大小
+    pub fn entries(&self) -> Result<Vec<Entry>> {
+        fn walk(dir: &Dir, prefix: &str, entries: &mut Vec<Entry>) -> Result<()> {
+            for (name, file) in &dir.files {
+                entries.push(Entry {
+                    path: format!("{}/{}", prefix, name),
+                    mime: file.mime.clone(),
+                    compress: file.compress,
+                    size: file.clone().decompressed_data()?.len() as u64,
+                    compressed_size: file.data.len() as u64,
+                });
+            }
+            for (name, dir) in &dir.dirs {
+                walk(dir, &format!("{}/{}", prefix, name), entries)?;
+            }
+            Ok(())
+        }
+        let mut entries = Vec::new();
+        walk(&self.fs, "", &mut entries)?;
+        Ok(entries)
+    }
+}
+
+// 打包时对每个文件依次执行的处理步骤
+#[cfg(feature = "bundler")]
+struct Pipeline {
+    codec: Compress,
+    level: u32,
+    window: u32,
+    renderer: Option<template::Renderer>,
+    minifier: Option<minify::Minifier>,
+    ignore: Vec<glob::Pattern>,
+}
+
+#[cfg(feature = "bundler")]
+impl Pipeline {
+    // 每个挂载点可以使用自己的压缩参数和忽略规则
+    fn new(config: &Config, compression: &Compression, ignore: &[String]) -> Result<Self> {
+        let (codec, level, window) = compression.params()?;
+        Ok(Self {
+            codec,
+            level,
+            window,
+            renderer: template::Renderer::new(config)?,
+            minifier: minify::Minifier::new(&config.minify)?,
+            ignore: glob_patterns(ignore)?,
+        })
+    }
+
+    fn process(&self, path: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+        let data = match &self.renderer {
+            Some(renderer) => renderer.render(path, data)?,
+            None => data,
+        };
+        match &self.minifier {
+            Some(minifier) => minifier.minify(path, data),
+            None => Ok(data),
+        }
+    }
+
+    fn compress(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        match self.codec {
+            Compress::Brotli => {
+                let mut buffer = Vec::new();
+                let mut reader =
+                    brotli::CompressorReader::new(data.as_slice(), 4096, self.level, self.window);
+                reader.read_to_end(&mut buffer)?;
+                Ok(buffer)
+            }
+            Compress::None => Ok(data),
+        }
+    }
+}
+
+// 读取并处理后、压缩前的文件
+#[cfg(feature = "bundler")]
+struct Source {
+    path: String,
+    data: Vec<u8>,
+    size: u64,
+    pipeline: usize,
+}
+
+#[cfg(feature = "bundler")]
+impl Pipeline {
+    // 读取目录或 zip/tar 归档中的文件，index 为该 Pipeline 的序号
+    fn read(&self, index: usize, source: &Path, prefix: &str) -> Result<Vec<Source>> {
+        let mut files = Vec::new();
+        if archive::is_archive(source) {
+            read_archive(source, &self.ignore, &mut files)?;
+        } else {
+            read_dir(source, source, &self.ignore, &mut files)?;
+        }
+        files
+            .into_iter()
+            .map(|(name, data)| {
+                let path = format!("{}{}", prefix, name);
+                Ok(Source {
+                    size: data.len() as u64,
+                    data: self.process(&path, data)?,
+                    path,
+                    pipeline: index,
+                })
+            })
+            .collect()
+    }
+}
+
+// 遍历本地目录，返回形如 "/build/bundle.js" 的相对路径与文件内容
+#[cfg(feature = "bundler")]
+fn read_dir(
+    root: &Path,
+    path: &Path,
+    ignore: &[glob::Pattern],
+    files: &mut Vec<(String, Vec<u8>)>,
+) -> Result<()> {
+    // 遍历目录
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        // 获取目录/文件名，如果为".."则跳过
+        match path.file_name() {
+            Some(s) if s.to_str().is_some() => (),
+            _ => break,
+        };
+        // 忽略规则相对于当前挂载点的根目录
+        let relative_path = entry_path(root, &path);
+        if glob_matches_any(ignore, &relative_path) {
+            continue;
+        }
+        // 优先填充文件
+        if path.is_file() {
+            files.push((relative_path, fs::read(&path)?));
+        } else if path.is_dir() {
+            read_dir(root, &path, ignore, files)?;
+        }
+    }
+    Ok(())
+}
+
+// 读取 zip/tar 归档，不解压到磁盘
+#[cfg(feature = "bundler")]
+fn read_archive(
+    archive: &Path,
+    ignore: &[glob::Pattern],
+    files: &mut Vec<(String, Vec<u8>)>,
+) -> Result<()> {
+    for (name, data) in archive::read(archive)? {
+        // 归档中的目录不一定有单独的条目，因此需要检查每一级父目录
+        if !is_ignored(ignore, &name) {
+            files.push((format!("/{}", name), data));
+        }
+    }
+    Ok(())
 }
 
 #[cfg(feature = "bundler")]
 impl Dir {
-    // 使用本地文件系统填充 Dir 结构体
-    fn fill_with<P: AsRef<path::Path>>(
+    // 压缩文件并放入对应的目录，与挂载点重叠的目录会被合并
+    fn add_file(
         &mut self,
-        root: P,
-        path: P,
-        length: &mut u64,
+        source: Source,
+        pipeline: &Pipeline,
+        stats: &mut BuildStats,
     ) -> Result<()> {
-        // 遍历目录
-        for entry in fs::read_dir(path)? {
-            let path = entry?.path();
-            // 获取目录/文件名，如果为".."则跳过
-            let name = match path.file_name() {
-                Some(s) => match s.to_str() {
-                    Some(s) => s.to_string(),
-                    None => break,
-                },
-                None => break,
-            };
-            // 优先填充文件
-            if path.is_file() {
-                let mut source = fs::File::open(&path)?;
-                let mime = new_mime_guess::from_path(&path)
-                    .first_or_octet_stream()
-                    .to_string();
-                let mut data = brotli::CompressorReader::new(&mut source, 4096, 9, 21);
-                let mut buffer = Vec::new();
-                data.read_to_end(&mut buffer)?;
-                let size = buffer.len();
-                let file = File {
-                    mime,
-                    data: buffer,
-                    compress: Compress::Brotli,
-                };
-                *length += size as u64;
-                self.files.push((name, file));
-            } else if path.is_dir() {
-                // 构造子目录
-                let mut dir = Dir {
-                    files: Vec::new(),
-                    dirs: Vec::new(),
-                };
-                // 填充子目录
-                dir.fill_with(root.as_ref(), &path, length)?;
-                self.dirs.push((name, dir));
-            }
+        let (dir, name) = source.path.rsplit_once('/').unwrap_or(("", &source.path));
+        let dir = self.subdir(dir);
+        if dir.files.iter().any(|(n, _)| n == name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} is provided by more than one source", source.path),
+            ));
         }
+        let mime = new_mime_guess::from_path(&source.path)
+            .first_or_octet_stream()
+            .to_string();
+        let name = name.to_string();
+        let buffer = pipeline.compress(source.data)?;
+        stats.files += 1;
+        stats.original_size += source.size;
+        stats.compressed_size += buffer.len() as u64;
+        stats.entries.push(Entry {
+            path: source.path,
+            mime: mime.clone(),
+            compress: pipeline.codec,
+            size: source.size,
+            compressed_size: buffer.len() as u64,
+        });
+        let file = File {
+            mime,
+            data: buffer,
+            compress: pipeline.codec,
+        };
+        dir.files.push((name, file));
         Ok(())
     }
+
+    // 返回指定路径的子目录，不存在时创建
+    fn subdir(&mut self, path: &str) -> &mut Dir {
+        let mut dir = self;
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            let index = match dir.dirs.iter().position(|(n, _)| n == name) {
+                Some(index) => index,
+                None => {
+                    dir.dirs.push((name.to_string(), Dir::default()));
+                    dir.dirs.len() - 1
+                }
+            };
+            dir = &mut dir.dirs[index].1;
+        }
+        dir
+    }
 }
 
 #[cfg(feature = "bundler")]
 impl Data {
     pub fn build_from_dir<P: AsRef<path::Path>>(
         source: P,
-        window_attr: WindowAttr,
-        webview_attr: WebViewAttr,
-    ) -> Result<Vec<u8>> {
-        let embed_fs = Self::from_dir(source, window_attr, webview_attr)?;
-        embed_fs.build()
+        config: &Config,
+    ) -> Result<(Vec<u8>, BuildStats)> {
+        let (embed_fs, stats) = Self::from_dir(source, config)?;
+        Ok((embed_fs.build()?, stats))
     }
 
-    fn from_dir<P: AsRef<path::Path>>(
-        source: P,
-        window_attr: WindowAttr,
-        webview_attr: WebViewAttr,
-    ) -> Result<Self> {
-        let source = source.as_ref();
-        let mut length: u64 = 0;
-        let mut dir = Dir {
-            files: Vec::new(),
-            dirs: Vec::new(),
-        };
-        dir.fill_with(source, source, &mut length)?;
-        Ok(Self {
-            fs: dir,
-            window_attr,
-            webview_attr,
-        })
+    fn from_dir<P: AsRef<path::Path>>(source: P, config: &Config) -> Result<(Self, BuildStats)> {
+        let mut sources = Vec::new();
+        let mut pipelines = vec![Pipeline::new(config, &config.compression, &config.ignore)?];
+        sources.extend(pipelines[0].read(0, source.as_ref(), "")?);
+        // 先填充较短的前缀，嵌套的挂载点会合并到外层挂载点的目录中
+        let mut mounts = config.mounts.iter().collect::<Vec<_>>();
+        mounts.sort_by_key(|mount| mount.prefix.len());
+        for mount in mounts {
+            let compression = mount.compression.as_ref().unwrap_or(&config.compression);
+            let pipeline = Pipeline::new(config, compression, &mount.ignore)?;
+            sources.extend(pipeline.read(pipelines.len(), &mount.source, &mount.prefix()?)?);
+            pipelines.push(pipeline);
+        }
+        // 所有文件读取完成后才能计算指纹并改写引用
+        let mut webview_attr = config.webview_attr()?;
+        if let Some(fingerprinter) = fingerprint::Fingerprinter::new(&config.fingerprint)? {
+            webview_attr.immutable = fingerprinter.apply(&mut sources, &webview_attr);
+        }
+        let mut stats = BuildStats::default();
+        if config.check_references != CheckReferences::Off {
+            stats.broken_references = reference::check(&sources, &webview_attr);
+        }
+        let mut dir = Dir::default();
+        for source in sources {
+            let pipeline = &pipelines[source.pipeline];
+            dir.add_file(source, pipeline, &mut stats)?;
+        }
+        Ok((
+            Self {
+                fs: dir,
+                window_attr: config.window_attr()?,
+                webview_attr,
+            },
+            stats,
+        ))
     }
 
     fn build(self) -> Result<Vec<u8>> {
@@ -308,7 +813,7 @@ impl Data {
         Ok(target)
     }
 
-    pub fn pack<P: AsRef<path::Path>>(config_path: P) -> anyhow::Result<()> {
+    pub fn pack<P: AsRef<path::Path>>(config_path: P) -> anyhow::Result<BuildStats> {
         let config_path = config_path.as_ref().canonicalize()?;
         let config: Config = toml::from_str(fs::read_to_string(&config_path)?.as_str())?;
         let source = match config_path.parent() {
@@ -319,11 +824,11 @@ impl Data {
             Some(path) => normalize_path(&path.join(&config.target)),
             None => normalize_path(&config.target),
         };
-        fs::write(
-            target,
-            Self::build_from_dir(source, config.window_attr()?, config.webview_attr()?)?,
-        )?;
-        Ok(())
+        let mut config = config;
+        config.resolve_mounts(config_path.parent().unwrap_or_else(|| Path::new(".")))?;
+        let (data, stats) = Self::build_from_dir(source, &config)?;
+        fs::write(target, data)?;
+        Ok(stats)
     }
 }
 
@@ -344,12 +849,177 @@ impl Default for Config {
             transparent: false,
             decorations: true,
             always_on_top: false,
+            resize_border: 0,
+            close_behavior: CloseBehavior::default(),
             icon: None,
             spa: false,
             url: Some("/index.html".into()),
             html: None,
             initialization_script: None,
             manifest: None,
+            ignore: Vec::new(),
+            mounts: Vec::new(),
+            embed: Embed::default(),
+            compression: Compression::default(),
+            not_found: None,
+            error_page: None,
+            protocol: Protocol::default(),
+            serve_mode: ServeMode::default(),
+            window_api: Vec::new(),
+            routes: Vec::new(),
+            headers: Vec::new(),
+            csp: None,
+            csp_meta: false,
+            minify: Minify::default(),
+            fingerprint: Fingerprint::default(),
+            template: Template::default(),
+            env: BTreeMap::new(),
+            check_references: CheckReferences::default(),
+            budgets: Budgets::default(),
+        }
+    }
+}
+
+#[cfg(feature = "bundler")]
+impl Compression {
+    // 返回 (编码, 压缩等级, 窗口大小)，显式设置的值优先于预设
+    pub fn params(&self) -> Result<(Compress, u32, u32)> {
+        let (level, window) = match self.preset.unwrap_or(CompressionPreset::Default) {
+            CompressionPreset::Fast => (1, 18),
+            CompressionPreset::Default => (9, 21),
+            CompressionPreset::Max => (11, 24),
+        };
+        let codec = self.codec.unwrap_or(Compress::Brotli);
+        let level = self.level.unwrap_or(level);
+        let window = self.window.unwrap_or(window);
+        // 不压缩时等级与窗口大小没有意义，不做检查
+        if codec == Compress::None {
+            return Ok((codec, level, window));
+        }
+        if level > 11 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("compression level {} out of range (0-11)", level),
+            ));
+        }
+        if !(10..=24).contains(&window) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("compression window {} out of range (10-24)", window),
+            ));
+        }
+        Ok((codec, level, window))
+    }
+}
+
+impl ByteSize {
+    // 支持 "2 MiB"、"500KB"、"1024" 等写法，KiB/MiB 按 1024 计算，KB/MB 按 1000 计算
+    pub fn bytes(&self) -> Result<u64> {
+        let text = match self {
+            Self::Bytes(bytes) => return Ok(*bytes),
+            Self::Text(text) => text.trim(),
+        };
+        let split = text
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(text.len());
+        let (number, unit) = text.split_at(split);
+        let number: f64 = number.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid size \"{}\"", text),
+            )
+        })?;
+        let scale: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "k" | "kib" => 1 << 10,
+            "m" | "mib" => 1 << 20,
+            "g" | "gib" => 1 << 30,
+            "kb" => 1000,
+            "mb" => 1000 * 1000,
+            "gb" => 1000 * 1000 * 1000,
+            unit => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown size unit \"{}\" in \"{}\"", unit, text),
+                ))
+            }
+        };
+        Ok((number * scale as f64) as u64)
+    }
+}
+
+#[cfg(feature = "bundler")]
+impl Mount {
+    // 规范化后的前缀，如 "/docs"，不允许挂载到根目录
+    pub fn prefix(&self) -> Result<String> {
+        let prefix = self.prefix.trim_end_matches('/');
+        let valid = prefix.starts_with('/')
+            && prefix[1..]
+                .split('/')
+                .all(|name| !name.is_empty() && name != "." && name != "..");
+        if !valid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid mount prefix \"{}\"", self.prefix),
+            ));
+        }
+        Ok(prefix.to_string())
+    }
+
+    // 将请求路径映射到挂载的本地目录，不属于该挂载点时返回 None
+    pub fn local_path(&self, path: &str) -> Option<PathBuf> {
+        let rest = path.strip_prefix(self.prefix.trim_end_matches('/'))?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+        Some(self.source.join(rest.trim_start_matches('/')))
+    }
+}
+
+impl Protocol {
+    pub fn origin(&self) -> String {
+        format!("{}://{}", self.scheme, self.host)
+    }
+
+    pub fn uri(&self, path: &str) -> String {
+        self.origin() + path
+    }
+
+    // scheme 需符合 RFC 3986，且不能覆盖浏览器内置的协议
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        let scheme = self.scheme.as_str();
+        if !scheme.starts_with(|c: char| c.is_ascii_lowercase())
+            || !scheme
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "+-.".contains(c))
+        {
+            return invalid(format!("invalid protocol scheme \"{}\"", scheme));
+        }
+        if matches!(
+            scheme,
+            "http" | "https" | "file" | "data" | "blob" | "about" | "javascript" | "ws" | "wss"
+        ) {
+            return invalid(format!("protocol scheme \"{}\" is reserved", scheme));
+        }
+        if self.host.is_empty()
+            || !self
+                .host
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-.".contains(c))
+        {
+            return invalid(format!("invalid protocol host \"{}\"", self.host));
+        }
+        Ok(())
+    }
+}
+
+impl BuildStats {
+    pub fn ratio(&self) -> f64 {
+        if self.original_size == 0 {
+            1.0
+        } else {
+            self.compressed_size as f64 / self.original_size as f64
         }
     }
 }
@@ -370,13 +1040,67 @@ impl Config {
             transparent: self.transparent,
             decorations: self.decorations,
             always_on_top: self.always_on_top,
+            resize_border: self.resize_border,
+            close_behavior: self.close_behavior,
             icon: match &self.icon {
                 Some(path) => Some(load_icon(path.as_path())?),
                 None => None,
             },
         })
     }
+    // 将挂载点的 source 解析为绝对路径，相对路径以配置文件所在目录为基准
+    pub fn resolve_mounts(&mut self, base: &Path) -> Result<()> {
+        for mount in &mut self.mounts {
+            mount.prefix()?;
+            mount.source = base.join(&mount.source).canonicalize().map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!(
+                        "failed to read mount source {}: {}",
+                        mount.source.display(),
+                        e
+                    ),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
     pub fn webview_attr(&self) -> Result<WebViewAttr> {
+        self.protocol.validate()?;
+        if let Some(name) = self
+            .window_api
+            .iter()
+            .find(|name| *name != "*" && !WINDOW_API.contains(&name.as_str()))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "unknown window_api \"{}\", expected \"*\" or one of {}",
+                    name,
+                    WINDOW_API.join(", ")
+                ),
+            ));
+        }
+        for rule in &self.headers {
+            glob::Pattern::new(rule.path.trim_start_matches('/')).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid header rule path \"{}\": {}", rule.path, e),
+                )
+            })?;
+        }
+        for route in &self.routes {
+            if route.to.contains("://") && !route.is_redirect() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "route \"{}\" points to an external URL and needs a redirect status",
+                        route.from
+                    ),
+                ));
+            }
+        }
         Ok(WebViewAttr {
             visible: self.visible,
             transparent: self.transparent,
@@ -390,20 +1114,113 @@ impl Config {
                 Some(path) => fs::read_to_string(path.as_path()).ok(),
                 None => None,
             },
+            headers: self.headers.clone(),
+            csp: self.csp.as_ref().map(Csp::policy),
+            csp_meta: self.csp_meta,
+            routes: self.routes.clone(),
+            not_found: self.not_found.clone(),
+            error_page: self.error_page.clone(),
+            protocol: self.protocol.clone(),
+            serve_mode: self.serve_mode,
+            immutable: Vec::new(),
+            window_api: self.window_api.clone(),
+            header_patterns: OnceLock::new(),
         })
     }
 }
 
+impl WebViewAttr {
+    // 按顺序查找第一条匹配的路由规则
+    pub fn route(&self, path: &str) -> Option<Resolution> {
+        self.routes.iter().find_map(|route| route.resolve(path))
+    }
+
+    // 按顺序应用所有匹配的规则，后面的规则覆盖前面的同名响应头，返回 (MIME 覆盖, 额外响应头)
+    pub fn headers_for(&self, path: &str) -> (Option<String>, Vec<(String, String)>) {
+        let mut mime = None;
+        let mut headers: Vec<(String, String)> = Vec::new();
+        // 带指纹的文件内容不会改变，可以一直缓存
+        if self
+            .immutable
+            .binary_search_by(|p| p.as_str().cmp(path))
+            .is_ok()
+        {
+            headers.push((
+                "Cache-Control".to_string(),
+                "public, max-age=31536000, immutable".to_string(),
+            ));
+        }
+        let patterns = self.header_patterns.get_or_init(|| {
+            self.headers
+                .iter()
+                .map(|rule| glob::Pattern::new(rule.path.trim_start_matches('/')).ok())
+                .collect()
+        });
+        let relative_path = path.trim_start_matches('/');
+        for (rule, pattern) in self.headers.iter().zip(patterns) {
+            if !matches!(pattern, Some(pattern) if pattern.matches_with(relative_path, glob_options()))
+            {
+                continue;
+            }
+            if rule.mime.is_some() {
+                mime = rule.mime.clone();
+            }
+            for (name, value) in &rule.headers {
+                headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+                headers.push((name.clone(), value.clone()));
+            }
+        }
+        (mime, headers)
+    }
+}
+
 #[cfg(feature = "runtime")]
 pub fn load<P: AsRef<path::Path> + Copy>(path: P) -> Result<Data> {
     Data::new(path)
 }
 
 #[cfg(feature = "bundler")]
-pub fn pack<P: AsRef<path::Path>>(config: P) -> anyhow::Result<()> {
+pub fn pack<P: AsRef<path::Path>>(config: P) -> anyhow::Result<BuildStats> {
     Data::pack(config)
 }
 
+// 将打包数据附加到运行时上，Section 模式下写入独立的 ELF 节，否则追加到文件末尾
+#[cfg(feature = "bundler")]
+pub fn embed(runtime: &[u8], payload: &[u8], embed: Embed) -> Result<Vec<u8>> {
+    match embed {
+        Embed::Trailer => {
+            let mut target = Vec::with_capacity(runtime.len() + payload.len());
+            target.extend(runtime);
+            target.extend(payload);
+            Ok(target)
+        }
+        Embed::Section => elf::add_section(runtime, SECTION_NAME, payload),
+    }
+}
+
+#[cfg(feature = "bundler")]
+fn is_html(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.ends_with(".html") || path.ends_with(".htm")
+}
+
+#[cfg(feature = "bundler")]
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+// 返回相对于 root 的、以 / 分隔的路径，如 "/build/bundle.js"
+#[cfg(feature = "bundler")]
+fn entry_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let mut entry = String::new();
+    for component in relative.components() {
+        entry.push('/');
+        entry.push_str(&component.as_os_str().to_string_lossy());
+    }
+    entry
+}
+
 #[cfg(feature = "bundler")]
 fn load_icon(path: &Path) -> Result<Icon> {
     let image = image::open(path)
@@ -416,6 +1233,69 @@ fn load_icon(path: &Path) -> Result<Icon> {
     })
 }
 
+fn glob_options() -> glob::MatchOptions {
+    glob::MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    }
+}
+
+// 开头的 / 可省略，"build/*.js" 与 "/build/*.js" 等价
+pub fn glob_patterns(patterns: &[String]) -> Result<Vec<glob::Pattern>> {
+    patterns
+        .iter()
+        .map(|p| {
+            glob::Pattern::new(p.trim_start_matches('/'))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+        })
+        .collect()
+}
+
+pub fn glob_matches_any(patterns: &[glob::Pattern], path: &str) -> bool {
+    let path = path.trim_start_matches('/');
+    patterns
+        .iter()
+        .any(|p| p.matches_with(path, glob_options()))
+}
+
+// 路径本身或任意一级父目录匹配忽略规则
+pub fn is_ignored(patterns: &[glob::Pattern], path: &str) -> bool {
+    let path = path.trim_start_matches('/');
+    path.match_indices('/')
+        .map(|(i, _)| &path[..i])
+        .chain([path])
+        .any(|path| glob_matches_any(patterns, path))
+}
+
+// 解码地址中的 %XX 转义，如 "/my%20file.png" -> "/my file.png"，
+// 不完整的转义保持原样，解码结果不是 UTF-8 时返回原地址
+pub fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes[i] {
+            b'%' => bytes
+                .get(i + 1..i + 3)
+                .and_then(|h| Some(hex(h[0])? << 4 | hex(h[1])?)),
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| path.to_string())
+}
+
 pub fn normalize_path(path: &Path) -> PathBuf {
     let mut components = path.components().peekable();
     let mut ret = if let Some(c @ Component::Prefix(..)) = components.peek().cloned() {
@@ -442,3 +1322,434 @@ pub fn normalize_path(path: &Path) -> PathBuf {
     }
     ret
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "bundler")]
+    fn sample_data() -> Data {
+        let config = Config::default();
+        let file = File {
+            mime: "text/html".into(),
+            data: b"<h1>hello</h1>".to_vec(),
+            compress: Compress::None,
+        };
+        Data {
+            window_attr: config.window_attr().unwrap(),
+            webview_attr: config.webview_attr().unwrap(),
+            fs: Dir {
+                files: Vec::new(),
+                dirs: vec![(
+                    "app".into(),
+                    Dir {
+                        files: vec![("index.html".into(), file)],
+                        dirs: Vec::new(),
+                    },
+                )],
+            },
+        }
+    }
+
+    #[cfg(feature = "bundler")]
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("neutauri_{}_{}", std::process::id(), name))
+    }
+
+    // 打包数据嵌入运行时后再读取出来
+    #[cfg(all(feature = "bundler", feature = "runtime"))]
+    #[test]
+    fn embed_and_load() {
+        let runtime = elf::tests::minimal_elf(true, true);
+        let payload = sample_data().build().unwrap();
+        for embed in [Embed::Trailer, Embed::Section] {
+            let path = temp_path(&format!("{:?}", embed));
+            fs::write(&path, super::embed(&runtime, &payload, embed).unwrap()).unwrap();
+            let (data, info) = Data::inspect(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(info.embed, embed);
+            assert_eq!(info.size, payload.len() as u64);
+            // 数据之前的部分都属于运行时，Section 模式下数据之后还有节名字符串表与节头表
+            assert_eq!(info.offset, runtime.len() as u64);
+            assert_eq!(info.version, "v02");
+            let mut file = data.open("/app/index.html").unwrap();
+            assert_eq!(file.decompressed_data().unwrap(), b"<h1>hello</h1>");
+            assert_eq!(file.mimetype(), "text/html");
+            assert!(data.open("/app/missing.html").is_err());
+        }
+    }
+
+    #[cfg(all(feature = "bundler", feature = "runtime"))]
+    #[test]
+    fn load_rejects_broken_elf() {
+        let runtime = elf::tests::minimal_elf(true, true);
+        let payload = sample_data().build().unwrap();
+        let mut target = super::embed(&runtime, &payload, Embed::Section).unwrap();
+        // 截断节头表，不能静默回退到 trailer
+        target.truncate(target.len() - 8);
+        let path = temp_path("broken");
+        fs::write(&path, target).unwrap();
+        let error = Data::new(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(error.to_string().contains("ELF"), "{}", error);
+        assert_ne!(error.kind(), io::ErrorKind::NotFound);
+        // 没有内嵌数据的程序返回 NotFound
+        let path = temp_path("plain");
+        fs::write(&path, &runtime).unwrap();
+        let error = Data::new(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[cfg(feature = "bundler")]
+    #[test]
+    fn compression_params() {
+        let params = |compression: Compression| compression.params().ok();
+        assert_eq!(
+            params(Compression::default()),
+            Some((Compress::Brotli, 9, 21))
+        );
+        let fast = Compression {
+            preset: Some(CompressionPreset::Fast),
+            ..Default::default()
+        };
+        assert_eq!(params(fast), Some((Compress::Brotli, 1, 18)));
+        // 显式设置的值覆盖预设
+        let max = Compression {
+            preset: Some(CompressionPreset::Max),
+            codec: Some(Compress::None),
+            level: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(params(max), Some((Compress::None, 5, 24)));
+        let level = Compression {
+            level: Some(12),
+            ..Default::default()
+        };
+        assert!(level.params().is_err());
+        let window = Compression {
+            window: Some(9),
+            ..Default::default()
+        };
+        assert!(window.params().is_err());
+        let none = Compression {
+            codec: Some(Compress::None),
+            level: Some(12),
+            window: Some(9),
+            ..Default::default()
+        };
+        assert_eq!(params(none), Some((Compress::None, 12, 9)));
+    }
+
+    #[cfg(feature = "bundler")]
+    #[test]
+    fn compression_from_toml() {
+        let compression: Compression =
+            toml::from_str("preset = \"max\"\ncodec = \"none\"\nwindow = 22").unwrap();
+        assert_eq!(compression.preset, Some(CompressionPreset::Max));
+        assert_eq!(compression.codec, Some(Compress::None));
+        assert_eq!(compression.window, Some(22));
+        assert_eq!(compression.level, None);
+    }
+
+    #[test]
+    fn byte_sizes() {
+        let bytes = |text: &str| ByteSize::Text(text.to_string()).bytes().ok();
+        assert_eq!(ByteSize::Bytes(42).bytes().ok(), Some(42));
+        assert_eq!(bytes("1024"), Some(1024));
+        assert_eq!(bytes("2 MiB"), Some(2 << 20));
+        assert_eq!(bytes("1.5k"), Some(1536));
+        assert_eq!(bytes("500KB"), Some(500_000));
+        assert_eq!(bytes(" 1 gb "), Some(1_000_000_000));
+        assert_eq!(bytes("3 pb"), None);
+        assert_eq!(bytes("MiB"), None);
+    }
+
+    #[cfg(all(feature = "bundler", feature = "runtime"))]
+    #[test]
+    fn load_rejects_other_versions() {
+        let mut payload = sample_data().build().unwrap();
+        payload[..MAGIC_NUMBER_START.len()].copy_from_slice(b"NEUTFSv01");
+        let path = temp_path("version");
+        fs::write(&path, payload).unwrap();
+        let error = Data::new(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(
+            error.to_string().contains("format v01 is not supported"),
+            "{}",
+            error
+        );
+        // 嵌入程序的旧版本数据同样报告版本错误，运行时不能把它当成没有数据
+        for embed in [Embed::Trailer, Embed::Section] {
+            let runtime = elf::tests::minimal_elf(true, true);
+            let mut payload = sample_data().build().unwrap();
+            payload[..MAGIC_NUMBER_START.len()].copy_from_slice(b"NEUTFSv01");
+            let path = temp_path(&format!("version-{:?}", embed));
+            fs::write(&path, super::embed(&runtime, &payload, embed).unwrap()).unwrap();
+            let error = Data::new(&path).unwrap_err();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(error.to_string().contains("format v01"), "{}", error);
+        }
+    }
+
+    #[cfg(feature = "bundler")]
+    #[test]
+    fn header_rules() {
+        let rule = |path: &str, mime: Option<&str>, headers: &[(&str, &str)]| HeaderRule {
+            path: path.to_string(),
+            mime: mime.map(str::to_string),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        };
+        let config = Config {
+            headers: vec![
+                rule("/**/*.wasm", Some("application/wasm"), &[]),
+                rule("assets/*", None, &[("Cache-Control", "max-age=60")]),
+                rule("/assets/*.js", None, &[("cache-control", "no-store")]),
+            ],
+            ..Default::default()
+        };
+        let mut webview_attr = config.webview_attr().unwrap();
+        let (mime, headers) = webview_attr.headers_for("/pkg/app.wasm");
+        assert_eq!(mime.as_deref(), Some("application/wasm"));
+        assert!(headers.is_empty());
+        // 后面的规则覆盖前面的同名响应头，名称不区分大小写
+        let (_, headers) = webview_attr.headers_for("/assets/app.js");
+        assert_eq!(
+            headers,
+            [("cache-control".to_string(), "no-store".to_string())]
+        );
+        // * 不匹配 /
+        let (_, headers) = webview_attr.headers_for("/assets/a/b.css");
+        assert!(headers.is_empty());
+        // 带指纹的文件默认长期缓存，规则中的同名响应头优先
+        webview_attr.immutable = vec![
+            "/app.123.css".to_string(),
+            "/assets/app.123.css".to_string(),
+        ];
+        let (_, headers) = webview_attr.headers_for("/app.123.css");
+        assert_eq!(headers[0].1, "public, max-age=31536000, immutable");
+        let (_, headers) = webview_attr.headers_for("/assets/app.123.css");
+        assert_eq!(
+            headers,
+            [("Cache-Control".to_string(), "max-age=60".to_string())]
+        );
+
+        let config = Config {
+            headers: vec![rule("/assets/[", None, &[])],
+            ..Default::default()
+        };
+        let error = config.webview_attr().unwrap_err();
+        assert!(error.to_string().contains("/assets/["), "{}", error);
+    }
+
+    #[cfg(feature = "bundler")]
+    #[test]
+    fn routes_in_order() {
+        let route = |from: &str, to: &str, status: Option<u16>| Route {
+            from: from.to_string(),
+            to: to.to_string(),
+            status,
+        };
+        let config = Config {
+            routes: vec![
+                route("/a/special", "/special.html", None),
+                route("/a/*", "/a.html", None),
+                route("/old/*", "/new/*", Some(302)),
+            ],
+            ..Default::default()
+        };
+        let webview_attr = config.webview_attr().unwrap();
+        assert_eq!(
+            webview_attr.route("/a/special"),
+            Some(Resolution::Rewrite("/special.html".into()))
+        );
+        assert_eq!(
+            webview_attr.route("/a/b"),
+            Some(Resolution::Rewrite("/a.html".into()))
+        );
+        assert_eq!(
+            webview_attr.route("/old/x"),
+            Some(Resolution::Redirect {
+                status: 302,
+                location: "/new/x".into()
+            })
+        );
+        assert_eq!(webview_attr.route("/b"), None);
+        // 外部地址只能重定向
+        let config = Config {
+            routes: vec![route("/ext", "https://example.com/", None)],
+            ..Default::default()
+        };
+        assert!(config.webview_attr().is_err());
+    }
+
+    #[test]
+    fn protocol() {
+        let protocol = |scheme: &str, host: &str| Protocol {
+            scheme: scheme.to_string(),
+            host: host.to_string(),
+        };
+        assert_eq!(
+            Protocol::default().uri("/index.html"),
+            "neu://localhost/index.html"
+        );
+        assert_eq!(
+            protocol("my-app+v1", "app.local").origin(),
+            "my-app+v1://app.local"
+        );
+        assert!(protocol("my-app+v1", "app.local").validate().is_ok());
+        for (scheme, host) in [
+            ("1app", "localhost"),
+            ("App", "localhost"),
+            ("a_b", "localhost"),
+            ("https", "localhost"),
+            ("file", "localhost"),
+            ("neu", ""),
+            ("neu", "Local"),
+            ("neu", "a/b"),
+        ] {
+            assert!(
+                protocol(scheme, host).validate().is_err(),
+                "{}://{}",
+                scheme,
+                host
+            );
+        }
+    }
+
+    #[test]
+    fn ignore_rules() {
+        let patterns = glob_patterns(&["/node_modules".into(), "**/*.map".into()]).unwrap();
+        assert!(is_ignored(&patterns, "/node_modules"));
+        assert!(is_ignored(&patterns, "/node_modules/a/b.js"));
+        assert!(is_ignored(&patterns, "build/app.js.map"));
+        assert!(!is_ignored(&patterns, "/src/node_modules.js"));
+        assert!(!is_ignored(&patterns, "/build/app.js"));
+    }
+
+    // 在临时目录中创建文件，返回目录路径
+    #[cfg(feature = "bundler")]
+    fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = temp_path(name);
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        root
+    }
+
+    #[cfg(feature = "bundler")]
+    fn paths(stats: &BuildStats) -> Vec<&str> {
+        let mut paths = stats
+            .entries
+            .iter()
+            .map(|e| e.path.as_str())
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths
+    }
+
+    #[cfg(feature = "bundler")]
+    #[test]
+    fn mount_prefix() {
+        let mount = |prefix: &str| Mount {
+            prefix: prefix.to_string(),
+            source: PathBuf::from("/srv/docs"),
+            ignore: Vec::new(),
+            compression: None,
+        };
+        assert_eq!(mount("/docs/").prefix().unwrap(), "/docs");
+        assert_eq!(mount("/a/b").prefix().unwrap(), "/a/b");
+        for prefix in ["/", "docs", "/a//b", "/a/../b", "/./a"] {
+            assert!(mount(prefix).prefix().is_err(), "{}", prefix);
+        }
+        assert_eq!(
+            mount("/docs").local_path("/docs/a/b.html"),
+            Some(PathBuf::from("/srv/docs/a/b.html"))
+        );
+        assert_eq!(
+            mount("/docs").local_path("/docs"),
+            Some(PathBuf::from("/srv/docs"))
+        );
+        assert_eq!(mount("/docs").local_path("/docsx/a.html"), None);
+    }
+
+    #[cfg(feature = "bundler")]
+    #[test]
+    fn mounts() {
+        let root = temp_dir(
+            "mounts",
+            &[
+                ("app/index.html", "index"),
+                ("app/docs/local.html", "local"),
+                ("app/app.js.map", "map"),
+                ("docs/guide.html", "guide"),
+                ("docs/draft.txt", "draft"),
+            ],
+        );
+        let mut config = Config {
+            ignore: vec!["*.map".into()],
+            mounts: vec![Mount {
+                prefix: "/docs/".into(),
+                source: PathBuf::from("docs"),
+                ignore: vec!["*.txt".into()],
+                compression: Some(Compression {
+                    codec: Some(Compress::None),
+                    ..Default::default()
+                }),
+            }],
+            ..Default::default()
+        };
+        config.resolve_mounts(&root).unwrap();
+        let (data, stats) = Data::from_dir(root.join("app"), &config).unwrap();
+        // 挂载点与 source 中的同名目录合并
+        assert_eq!(
+            paths(&stats),
+            ["/docs/guide.html", "/docs/local.html", "/index.html"]
+        );
+        let guide = stats
+            .entries
+            .iter()
+            .find(|e| e.path == "/docs/guide.html")
+            .unwrap();
+        assert_eq!(guide.compress, Compress::None);
+        let mut file = data.open("/docs/local.html").unwrap();
+        assert_eq!(file.decompressed_data().unwrap(), b"local");
+
+        // 两个来源提供同一个文件
+        fs::write(root.join("app/docs/guide.html"), "guide").unwrap();
+        let error = Data::from_dir(root.join("app"), &config).unwrap_err();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[cfg(all(feature = "bundler", feature = "runtime"))]
+    #[test]
+    fn bundle_from_archive() {
+        let archive = temp_path("source.zip");
+        let files = [
+            ("/index.html".to_string(), b"index".to_vec()),
+            ("/node_modules/a/index.js".to_string(), b"a".to_vec()),
+        ];
+        archive::write(&archive, &files).unwrap();
+        let config = Config {
+            ignore: vec!["node_modules".into()],
+            ..Default::default()
+        };
+        let result = Data::from_dir(&archive, &config);
+        fs::remove_file(&archive).unwrap();
+        let (data, stats) = result.unwrap();
+        assert_eq!(paths(&stats), ["/index.html"]);
+        // 导出后再打包得到相同的文件
+        let export = temp_path("export.tar.gz");
+        assert_eq!(data.export(&export).unwrap(), 1);
+        let (_, stats) = Data::from_dir(&export, &config).unwrap();
+        fs::remove_file(&export).unwrap();
+        assert_eq!(paths(&stats), ["/index.html"]);
+    }
+}
@@ -0,0 +1,277 @@
+use crate::{find_bytes, glob_matches_any, glob_patterns, Minify};
+use std::io::{self, Result};
+
+enum Kind {
+    Html,
+    Css,
+    Js,
+    Json,
+    Svg,
+}
+
+pub(crate) struct Minifier {
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+}
+
+impl Minifier {
+    pub(crate) fn new(config: &Minify) -> Result<Option<Self>> {
+        if !config.enable {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            include: glob_patterns(&config.include)?,
+            exclude: glob_patterns(&config.exclude)?,
+        }))
+    }
+
+    // 不匹配或不支持的文件原样返回
+    pub(crate) fn minify(&self, path: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+        if (!self.include.is_empty() && !glob_matches_any(&self.include, path))
+            || glob_matches_any(&self.exclude, path)
+        {
+            return Ok(data);
+        }
+        let extension = path
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase());
+        let kind = match extension.as_deref() {
+            Some("html" | "htm") => Kind::Html,
+            Some("css") => Kind::Css,
+            Some("js" | "mjs" | "cjs") => Kind::Js,
+            Some("json" | "webmanifest") => Kind::Json,
+            Some("svg") => Kind::Svg,
+            _ => return Ok(data),
+        };
+        // minify-js 遇到部分不支持的语法时会 panic，这里转换为普通错误
+        let minified = std::panic::catch_unwind(|| match kind {
+            Kind::Html => html(&data),
+            Kind::Css => css(&data),
+            Kind::Js => js(&data),
+            Kind::Json => Ok(json(&data)),
+            Kind::Svg => Ok(svg(&data)),
+        })
+        .unwrap_or_else(|_| Err("unsupported syntax".to_string()))
+        .map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("failed to minify {}: {}", path, e),
+            )
+        })?;
+        // 压缩结果反而更大时保留原文件
+        if minified.len() < data.len() {
+            Ok(minified)
+        } else {
+            Ok(data)
+        }
+    }
+}
+
+fn utf8(data: &[u8]) -> std::result::Result<&str, String> {
+    std::str::from_utf8(data).map_err(|e| e.to_string())
+}
+
+fn html(data: &[u8]) -> std::result::Result<Vec<u8>, String> {
+    utf8(data)?;
+    let cfg = minify_html::Cfg {
+        minify_css: true,
+        minify_js: true,
+        ..minify_html::Cfg::spec_compliant()
+    };
+    Ok(minify_html::minify(data, &cfg))
+}
+
+fn css(data: &[u8]) -> std::result::Result<Vec<u8>, String> {
+    use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+    let mut stylesheet =
+        StyleSheet::parse(utf8(data)?, ParserOptions::default()).map_err(|e| e.to_string())?;
+    stylesheet
+        .minify(MinifyOptions::default())
+        .map_err(|e| e.to_string())?;
+    let output = stylesheet
+        .to_css(PrinterOptions {
+            minify: true,
+            ..PrinterOptions::default()
+        })
+        .map_err(|e| e.to_string())?;
+    Ok(output.code.into_bytes())
+}
+
+fn js(data: &[u8]) -> std::result::Result<Vec<u8>, String> {
+    use minify_js::{Session, TopLevelMode};
+    // 普通脚本的顶层声明是全局变量，不能重命名，因此只有包含 import/export 语句时才按模块处理
+    let is_module = utf8(data)?.lines().any(|line| {
+        let line = line.trim_start();
+        line.starts_with("import ") || line.starts_with("import{") || line.starts_with("export ")
+    });
+    let mode = match is_module {
+        true => TopLevelMode::Module,
+        false => TopLevelMode::Global,
+    };
+    let mut output = Vec::new();
+    minify_js::minify(&Session::new(), mode, data, &mut output).map_err(|e| e.to_string())?;
+    Ok(output)
+}
+
+// 仅去掉字符串以外的空白，不重新序列化，避免改变数字精度和键顺序
+fn json(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for &byte in data {
+        if in_string {
+            output.push(byte);
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => (),
+            }
+        } else if !byte.is_ascii_whitespace() {
+            if byte == b'"' {
+                in_string = true;
+            }
+            output.push(byte);
+        }
+    }
+    output
+}
+
+// 去掉注释和标签之间的纯空白文本，文本内容元素中的空白会影响排版，保持原样
+fn svg(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut rest = data;
+    // 当前所在的 <text>、<tspan> 等元素的层数
+    let mut text_depth = 0usize;
+    while !rest.is_empty() {
+        if rest.starts_with(b"<!--") {
+            let end = find_bytes(rest, b"-->")
+                .map(|end| end + 3)
+                .unwrap_or(rest.len());
+            rest = &rest[end..];
+        } else if rest.starts_with(b"<![CDATA[") {
+            let end = find_bytes(rest, b"]]>")
+                .map(|end| end + 3)
+                .unwrap_or(rest.len());
+            output.extend(&rest[..end]);
+            rest = &rest[end..];
+        } else if rest[0] == b'<' {
+            let end = find_bytes(rest, b">")
+                .map(|end| end + 1)
+                .unwrap_or(rest.len());
+            let tag = &rest[..end];
+            if is_text_element(tag) && !tag.ends_with(b"/>") {
+                match tag.starts_with(b"</") {
+                    true => text_depth = text_depth.saturating_sub(1),
+                    false => text_depth += 1,
+                }
+            }
+            output.extend(tag);
+            rest = &rest[end..];
+        } else {
+            let end = find_bytes(rest, b"<").unwrap_or(rest.len());
+            if text_depth > 0 || !rest[..end].iter().all(|b| b.is_ascii_whitespace()) {
+                output.extend(&rest[..end]);
+            }
+            rest = &rest[end..];
+        }
+    }
+    output
+}
+
+fn is_text_element(tag: &[u8]) -> bool {
+    let name = tag
+        .iter()
+        .skip(1)
+        .skip_while(|&&b| b == b'/')
+        .take_while(|&&b| !(b.is_ascii_whitespace() || b == b'/' || b == b'>'))
+        .copied()
+        .collect::<Vec<_>>();
+    matches!(name.as_slice(), b"text" | b"tspan" | b"textPath")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minifier(include: &[&str], exclude: &[&str]) -> Minifier {
+        let config = Minify {
+            enable: true,
+            include: include.iter().map(|p| p.to_string()).collect(),
+            exclude: exclude.iter().map(|p| p.to_string()).collect(),
+        };
+        Minifier::new(&config).unwrap().unwrap()
+    }
+
+    fn minify(path: &str, data: &str) -> String {
+        let output = minifier(&[], &[]).minify(path, data.as_bytes().to_vec());
+        String::from_utf8(output.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn disabled() {
+        assert!(Minifier::new(&Minify::default()).unwrap().is_none());
+    }
+
+    #[test]
+    fn include_and_exclude() {
+        let data = b"{ \"a\": 1 }".to_vec();
+        let minifier = minifier(&["/data/*.json"], &["/data/keep.json"]);
+        assert_eq!(
+            minifier.minify("/data/a.json", data.clone()).unwrap(),
+            b"{\"a\":1}"
+        );
+        assert_eq!(
+            minifier.minify("/data/keep.json", data.clone()).unwrap(),
+            data
+        );
+        assert_eq!(minifier.minify("/other.json", data.clone()).unwrap(), data);
+        // 不支持的类型原样返回
+        assert_eq!(minify("/a.txt", "a   b"), "a   b");
+    }
+
+    #[test]
+    fn html_css_js() {
+        let html = minify(
+            "/index.html",
+            "<html>\n  <body>\n    <p>hello   world</p>\n  </body>\n</html>\n",
+        );
+        assert!(html.contains("<p>hello world"), "{}", html);
+        assert_eq!(
+            minify("/a.css", "a {\n  color: #ff0000;\n}\n"),
+            "a{color:red}"
+        );
+        // 普通脚本的全局变量名保持不变
+        let js = minify(
+            "/a.js",
+            "var counter = 1;\nfunction increase(value) {\n  return value + counter;\n}\n",
+        );
+        assert!(js.contains("counter") && js.contains("increase"), "{}", js);
+        assert!(js.len() < 60, "{}", js);
+    }
+
+    #[test]
+    fn invalid_js() {
+        let error = minifier(&[], &[])
+            .minify("/a.js", b"function (".to_vec())
+            .unwrap_err();
+        assert!(error.to_string().contains("/a.js"));
+    }
+
+    #[test]
+    fn json_keeps_strings() {
+        assert_eq!(
+            minify("/a.json", "{\n  \"a b\": \"c \\\" d\",\n  \"n\": 1.50\n}"),
+            "{\"a b\":\"c \\\" d\",\"n\":1.50}"
+        );
+    }
+
+    #[test]
+    fn svg_whitespace() {
+        let svg = "<svg>\n  <!-- icon -->\n  <g>\n    <path d=\"M0 0\"/>\n  </g>\n  <text x=\"0\"><tspan>a</tspan> <tspan>b</tspan></text>\n</svg>\n";
+        assert_eq!(
+            minify("/a.svg", svg),
+            "<svg><g><path d=\"M0 0\"/></g><text x=\"0\"><tspan>a</tspan> <tspan>b</tspan></text></svg>"
+        );
+    }
+}
@@ -0,0 +1,432 @@
+use crate::{is_html, percent_decode, Resolution, Source, WebViewAttr};
+use std::{collections::BTreeSet, ops::Range};
+
+// 找出打包后会 404 的引用，以及不存在的入口页面与错误页面
+pub(crate) fn check(sources: &[Source], webview_attr: &WebViewAttr) -> Vec<String> {
+    let paths = sources
+        .iter()
+        .map(|source| source.path.as_str())
+        .collect::<BTreeSet<_>>();
+    // 与运行时一致：以 / 结尾的地址指向目录下的 index.html
+    let contains = |path: &str| match path.ends_with('/') {
+        true => paths.contains(format!("{}index.html", path).as_str()),
+        false => paths.contains(path),
+    };
+    // 先经过路由规则，重定向的目标不在检查范围内
+    let exists = |path: &str| match webview_attr.route(path) {
+        Some(Resolution::Redirect { .. }) => true,
+        Some(Resolution::Rewrite(target)) => {
+            let end = target.find(['?', '#']).unwrap_or(target.len());
+            contains(&target[..end])
+        }
+        None => contains(path),
+    };
+    let mut report = Vec::new();
+    let url = match (&webview_attr.html, &webview_attr.url) {
+        (Some(_), _) => None,
+        (None, Some(url)) => Some(url.as_str()).filter(|url| url.starts_with('/')),
+        (None, None) => Some("/index.html"),
+    };
+    if let Some(url) = url {
+        let end = url.find(['?', '#']).unwrap_or(url.len());
+        if !exists(&url[..end]) {
+            report.push(format!("url = \"{}\" does not exist in the bundle", url));
+        }
+    }
+    for (name, page) in [
+        ("not_found", &webview_attr.not_found),
+        ("error_page", &webview_attr.error_page),
+    ] {
+        if let Some(page) = page {
+            if !exists(&format!("/{}", page.trim_start_matches('/'))) {
+                report.push(format!(
+                    "{} = \"{}\" does not exist in the bundle",
+                    name, page
+                ));
+            }
+        }
+    }
+    for source in sources {
+        let text = String::from_utf8_lossy(&source.data);
+        for (range, target) in find(&source.path, &source.data) {
+            // 单页应用中没有扩展名的地址交给前端路由处理
+            let name = target.rsplit('/').next().unwrap_or(&target);
+            if webview_attr.spa && !name.contains('.') {
+                continue;
+            }
+            if !exists(&target) {
+                report.push(format!(
+                    "{} references \"{}\", but {} does not exist",
+                    source.path, &text[range], target
+                ));
+            }
+        }
+    }
+    report
+}
+
+// 返回文件中的引用位置及其指向的打包路径，按扩展名区分 html、css 与 js
+pub(crate) fn find(path: &str, data: &[u8]) -> Vec<(Range<usize>, String)> {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return Vec::new(),
+    };
+    let extension = path
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_ascii_lowercase());
+    let spans = match extension.as_deref() {
+        _ if is_html(path) => markup_spans(text, true),
+        Some("css") => markup_spans(text, false),
+        Some("js" | "mjs") => script_spans(text),
+        _ => Vec::new(),
+    };
+    spans
+        .into_iter()
+        .filter_map(|range| {
+            let target = resolve(path, &text[range.clone()])?;
+            Some((range, target))
+        })
+        .collect()
+}
+
+// 查找 css 的 url()/@import 以及 html 的 src/href/poster/srcset 属性值
+fn markup_spans(text: &str, html: bool) -> Vec<Range<usize>> {
+    let lower = text.to_ascii_lowercase();
+    let bytes = lower.as_bytes();
+    let skip_whitespace = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        i
+    };
+    // 从 i 开始读取一个带引号的值，没有引号时读到 terminators 中的任一字符为止
+    let value = |i: usize, terminators: &[u8]| -> Option<Range<usize>> {
+        let (start, end) = match bytes.get(i)? {
+            quote @ (b'"' | b'\'') => {
+                let end = bytes[i + 1..].iter().position(|b| b == quote)?;
+                (i + 1, i + 1 + end)
+            }
+            _ => {
+                let end = bytes[i..]
+                    .iter()
+                    .position(|b| b.is_ascii_whitespace() || terminators.contains(b))
+                    .unwrap_or(bytes.len() - i);
+                (i, i + end)
+            }
+        };
+        Some(start..end)
+    };
+    let mut spans = Vec::new();
+    // url() 同时适用于 html 中的 <style> 与 style 属性
+    for (start, _) in lower.match_indices("url(") {
+        spans.extend(value(skip_whitespace(start + 4), b")"));
+    }
+    for (start, _) in lower.match_indices("@import") {
+        let i = skip_whitespace(start + 7);
+        if matches!(bytes.get(i), Some(b'"' | b'\'')) {
+            spans.extend(value(i, b";"));
+        }
+    }
+    if html {
+        // 属性只在标签内查找，内联脚本中的 src = "..." 是普通变量
+        let tags = tags(&lower);
+        for name in ["src", "href", "poster", "srcset"] {
+            for (start, _) in lower.match_indices(name) {
+                let preceded = start > 0 && bytes[start - 1].is_ascii_whitespace();
+                let i = skip_whitespace(start + name.len());
+                if !preceded
+                    || bytes.get(i) != Some(&b'=')
+                    || !tags.iter().any(|tag| tag.contains(&start))
+                {
+                    continue;
+                }
+                let range = match value(skip_whitespace(i + 1), b">") {
+                    Some(range) => range,
+                    None => continue,
+                };
+                if name != "srcset" {
+                    spans.push(range);
+                    continue;
+                }
+                // srcset 中每一项的第一个词是地址
+                let mut offset = range.start;
+                for candidate in text[range.clone()].split(',') {
+                    let trimmed = candidate.trim_start();
+                    let start = offset + candidate.len() - trimmed.len();
+                    let length = trimmed
+                        .find(|c: char| c.is_ascii_whitespace())
+                        .unwrap_or(trimmed.len());
+                    spans.push(start..start + length);
+                    offset += candidate.len() + 1;
+                }
+            }
+        }
+    }
+    spans.retain(|range| !range.is_empty());
+    spans.sort_by_key(|range| range.start);
+    spans
+}
+
+// 返回 html 中 < 与 > 之间的范围，跳过注释以及 script/style 元素的内容
+fn tags(lower: &str) -> Vec<Range<usize>> {
+    let bytes = lower.as_bytes();
+    let mut tags = Vec::new();
+    let mut i = 0;
+    while let Some(start) = lower[i..].find('<').map(|offset| i + offset) {
+        if lower[start..].starts_with("<!--") {
+            i = lower[start..]
+                .find("-->")
+                .map_or(bytes.len(), |end| start + end + 3);
+            continue;
+        }
+        // 引号中的 > 不结束标签
+        let mut quote = None;
+        let mut end = start + 1;
+        while end < bytes.len() {
+            match (quote, bytes[end]) {
+                (None, b'>') => break,
+                (None, b @ (b'"' | b'\'')) => quote = Some(b),
+                (Some(q), b) if b == q => quote = None,
+                _ => (),
+            }
+            end += 1;
+        }
+        tags.push(start..end);
+        i = end;
+        for name in ["script", "style"] {
+            if lower[start + 1..].starts_with(name) {
+                let close = format!("</{}", name);
+                i = lower[end..]
+                    .find(&close)
+                    .map_or(bytes.len(), |offset| end + offset);
+            }
+        }
+    }
+    tags
+}
+
+// 将引用解析为以 / 开头的打包路径，外部地址返回 None
+pub(crate) fn resolve(base: &str, reference: &str) -> Option<String> {
+    let end = reference.find(['?', '#']).unwrap_or(reference.len());
+    let path = &reference[..end];
+    let scheme = matches!(path.find(':'), Some(i) if !path[..i].contains('/'));
+    if path.is_empty() || path.starts_with("//") || scheme {
+        return None;
+    }
+    let path = percent_decode(path);
+    // 指向目录的引用保留结尾的 /，如 "docs/" 与 "./"
+    let dir = path.ends_with('/') || matches!(path.rsplit('/').next(), Some("." | ".."));
+    let joined = match path.starts_with('/') {
+        true => path.to_string(),
+        false => format!(
+            "{}/{}",
+            base.rsplit_once('/').map_or("", |(dir, _)| dir),
+            path
+        ),
+    };
+    let mut parts = Vec::new();
+    for part in joined.split('/') {
+        match part {
+            "" | "." => (),
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    match dir && !parts.is_empty() {
+        true => Some(format!("/{}/", parts.join("/"))),
+        false => Some(format!("/{}", parts.join("/"))),
+    }
+}
+
+// 查找 js 中的 import/export ... from 与 import() 的模块地址，只保留相对路径与根路径
+fn script_spans(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b'$';
+    let skip_whitespace = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        i
+    };
+    let mut spans = Vec::new();
+    for keyword in ["import", "from"] {
+        for (start, _) in text.match_indices(keyword) {
+            let end = start + keyword.len();
+            if (start > 0 && (is_word(bytes[start - 1]) || bytes[start - 1] == b'.'))
+                || matches!(bytes.get(end), Some(&b) if is_word(b))
+            {
+                continue;
+            }
+            let mut i = skip_whitespace(end);
+            if keyword == "import" && bytes.get(i) == Some(&b'(') {
+                i = skip_whitespace(i + 1);
+            }
+            let quote = match bytes.get(i) {
+                Some(quote @ (b'"' | b'\'' | b'`')) => *quote,
+                _ => continue,
+            };
+            let length = match bytes[i + 1..]
+                .iter()
+                .position(|&b| b == quote || b == b'\n')
+            {
+                Some(length) if bytes[i + 1 + length] == quote => length,
+                _ => continue,
+            };
+            let range = i + 1..i + 1 + length;
+            let specifier = &text[range.clone()];
+            if specifier.starts_with("./")
+                || specifier.starts_with("../")
+                || (specifier.starts_with('/') && !specifier.starts_with("//"))
+            {
+                spans.push(range);
+            }
+        }
+    }
+    spans.sort_by_key(|range| range.start);
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Route};
+
+    fn source(path: &str, data: &str) -> Source {
+        Source {
+            path: path.to_string(),
+            data: data.as_bytes().to_vec(),
+            size: data.len() as u64,
+            pipeline: 0,
+        }
+    }
+
+    fn targets(path: &str, data: &str) -> Vec<String> {
+        find(path, data.as_bytes())
+            .into_iter()
+            .map(|(_, target)| target)
+            .collect()
+    }
+
+    #[test]
+    fn resolve_paths() {
+        assert_eq!(resolve("/a/b.html", "c.png").unwrap(), "/a/c.png");
+        assert_eq!(resolve("/a/b.html", "../c.png?v=1#x").unwrap(), "/c.png");
+        assert_eq!(resolve("/a/b.html", "/./c//d.png").unwrap(), "/c/d.png");
+        assert!(resolve("/a.html", "https://example.com/a.png").is_none());
+        assert!(resolve("/a.html", "//example.com/a.png").is_none());
+        assert!(resolve("/a.html", "data:image/png;base64,").is_none());
+        assert!(resolve("/a.html", "#top").is_none());
+        // 目录引用保留结尾的 /，转义的字符被解码
+        assert_eq!(resolve("/a/b.html", "/").unwrap(), "/");
+        assert_eq!(resolve("/a/b.html", "./").unwrap(), "/a/");
+        assert_eq!(resolve("/a/b.html", "..").unwrap(), "/");
+        assert_eq!(resolve("/a/b.html", "docs/?v=1").unwrap(), "/a/docs/");
+        assert_eq!(resolve("/a.html", "my%20file.png").unwrap(), "/my file.png");
+        assert_eq!(resolve("/a.html", "100%.png").unwrap(), "/100%.png");
+    }
+
+    #[test]
+    fn html_references() {
+        let html = r#"<!-- <img src="old.png"> -->
+<link rel="stylesheet" href="app.css"><img src = 'a.png' srcset="b.png 1x, c.png 2x">
+<video poster=d.png></video><div style="background: url(e.png)"></div>
+<script>var src = "f.png"; img.href = "g.png";</script><p>src="h.png"</p>"#;
+        assert_eq!(
+            targets("/index.html", html),
+            ["/app.css", "/a.png", "/b.png", "/c.png", "/d.png", "/e.png"]
+        );
+    }
+
+    #[test]
+    fn css_references() {
+        let css = r#"@import "base.css"; @import url(theme.css); a { src: url( '../img/a.png' ) }"#;
+        assert_eq!(
+            targets("/css/app.css", css),
+            ["/css/base.css", "/css/theme.css", "/img/a.png"]
+        );
+    }
+
+    #[test]
+    fn script_references() {
+        let js = r#"import a from "./a.js"; export { b } from '../b.js';
+import("/c.js"); import 'lodash'; import x from "https://cdn.example/x.js";
+const from = "./not_a_module.js"; obj.import("./d.js");"#;
+        assert_eq!(targets("/src/main.js", js), ["/src/a.js", "/b.js", "/c.js"]);
+        assert!(targets("/a.png", "url(b.png)").is_empty());
+    }
+
+    #[test]
+    fn broken_references() {
+        let sources = [
+            source(
+                "/index.html",
+                r#"<a href="/about"><img src="missing.png"><img src="/old/a.png"><a href="/go">"#,
+            ),
+            source("/new/a.png", ""),
+        ];
+        let mut config = Config {
+            not_found: Some("404.html".into()),
+            routes: vec![
+                Route {
+                    from: "/old/*".into(),
+                    to: "/new/*".into(),
+                    status: None,
+                },
+                Route {
+                    from: "/go".into(),
+                    to: "https://example.com".into(),
+                    status: Some(302),
+                },
+            ],
+            ..Default::default()
+        };
+        let report = check(&sources, &config.webview_attr().unwrap());
+        assert_eq!(
+            report,
+            [
+                "not_found = \"404.html\" does not exist in the bundle",
+                "/index.html references \"/about\", but /about does not exist",
+                "/index.html references \"missing.png\", but /missing.png does not exist",
+            ]
+        );
+        // 单页应用中没有扩展名的地址交给前端路由，缺少入口页面时同样报告
+        config.spa = true;
+        config.url = Some("/app.html".into());
+        let report = check(&sources, &config.webview_attr().unwrap());
+        assert_eq!(report.len(), 3);
+        assert_eq!(
+            report[0],
+            "url = \"/app.html\" does not exist in the bundle"
+        );
+        assert!(report[2].contains("missing.png"));
+    }
+
+    // 目录引用按 <dir>/index.html 检查，转义的文件名解码后检查
+    #[test]
+    fn directory_references() {
+        let sources = [
+            source(
+                "/docs/guide.html",
+                r#"<a href="/"><a href="./"><a href="../docs/"><img src="my%20file.png">"#,
+            ),
+            source(
+                "/index.html",
+                r#"<a href="api/"><img src="docs/my%20file.png"><img src="a%20b.png">"#,
+            ),
+            source("/docs/index.html", ""),
+            source("/docs/my file.png", ""),
+        ];
+        let report = check(&sources, &Config::default().webview_attr().unwrap());
+        assert_eq!(
+            report,
+            [
+                "/index.html references \"api/\", but /api/ does not exist",
+                "/index.html references \"a%20b.png\", but /a b.png does not exist",
+            ]
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Route {
+    pub from: String,
+    pub to: String,
+    pub status: Option<u16>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    Redirect { status: u16, location: String },
+    Rewrite(String),
+}
+
+impl Route {
+    pub fn is_redirect(&self) -> bool {
+        matches!(self.status, Some(300..=399))
+    }
+
+    // 匹配成功时返回替换通配符后的目标
+    pub fn resolve(&self, path: &str) -> Option<Resolution> {
+        let captures = wildcard_match(&self.from, path)?;
+        let mut target = String::new();
+        let mut captures = captures.into_iter();
+        for (i, part) in self.to.split('*').enumerate() {
+            if i > 0 {
+                target.push_str(captures.next().unwrap_or_default());
+            }
+            target.push_str(part);
+        }
+        Some(match self.status {
+            Some(status) if self.is_redirect() => Resolution::Redirect {
+                status,
+                location: target,
+            },
+            _ => Resolution::Rewrite(target),
+        })
+    }
+}
+
+// * 匹配任意字符（包括 /），返回每个 * 匹配到的内容
+pub(crate) fn wildcard_match<'a>(pattern: &str, path: &'a str) -> Option<Vec<&'a str>> {
+    let parts = pattern.split('*').collect::<Vec<_>>();
+    let (first, rest) = parts.split_first()?;
+    let mut remaining = path.strip_prefix(first)?;
+    let mut offset = path.len() - remaining.len();
+    let mut captures = Vec::new();
+    if rest.is_empty() {
+        return match remaining.is_empty() {
+            true => Some(captures),
+            false => None,
+        };
+    }
+    for (i, part) in rest.iter().enumerate() {
+        let start = if i == rest.len() - 1 {
+            // 最后一段必须匹配结尾
+            if !remaining.ends_with(part) {
+                return None;
+            }
+            remaining.len() - part.len()
+        } else {
+            remaining.find(part)?
+        };
+        captures.push(&path[offset..offset + start]);
+        remaining = &remaining[start + part.len()..];
+        offset = path.len() - remaining.len();
+    }
+    Some(captures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(from: &str, to: &str, status: Option<u16>) -> Route {
+        Route {
+            from: from.to_string(),
+            to: to.to_string(),
+            status,
+        }
+    }
+
+    #[test]
+    fn exact() {
+        let route = route("/old", "/new", None);
+        assert_eq!(
+            route.resolve("/old"),
+            Some(Resolution::Rewrite("/new".into()))
+        );
+        assert_eq!(route.resolve("/old/"), None);
+        assert_eq!(route.resolve("/older"), None);
+    }
+
+    #[test]
+    fn wildcards() {
+        let posts = route("/blog/*/posts/*.html", "/posts/*-*.html", None);
+        assert_eq!(
+            posts.resolve("/blog/2023/posts/a/b.html"),
+            Some(Resolution::Rewrite("/posts/2023-a/b.html".into()))
+        );
+        assert_eq!(posts.resolve("/blog/2023/posts/a.htm"), None);
+        // 末尾的 * 可以匹配空字符串
+        let app = route("/app/*", "/app/index.html", None);
+        assert_eq!(
+            app.resolve("/app/"),
+            Some(Resolution::Rewrite("/app/index.html".into()))
+        );
+        assert_eq!(app.resolve("/app"), None);
+    }
+
+    #[test]
+    fn redirects() {
+        let redirect = route("/docs/*", "https://example.com/*", Some(301));
+        assert!(redirect.is_redirect());
+        assert_eq!(
+            redirect.resolve("/docs/a/b"),
+            Some(Resolution::Redirect {
+                status: 301,
+                location: "https://example.com/a/b".into()
+            })
+        );
+        // 非 3xx 状态码按改写处理
+        let rewrite = route("/x", "/y", Some(200));
+        assert!(!rewrite.is_redirect());
+        assert_eq!(
+            rewrite.resolve("/x"),
+            Some(Resolution::Rewrite("/y".into()))
+        );
+    }
+}
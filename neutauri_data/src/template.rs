@@ -0,0 +1,142 @@
+use crate::{find_bytes, glob_matches_any, glob_patterns, Config};
+use std::{
+    collections::BTreeMap,
+    io::{self, Result},
+};
+
+const ENV_PREFIX: &str = "NEUTAURI_PUBLIC_";
+
+pub(crate) struct Renderer {
+    include: Vec<glob::Pattern>,
+    vars: BTreeMap<String, String>,
+}
+
+impl Renderer {
+    // 只处理匹配 template.include 的文件
+    pub(crate) fn new(config: &Config) -> Result<Option<Self>> {
+        if config.template.include.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            include: glob_patterns(&config.template.include)?,
+            // 忽略名称或值不是 UTF-8 的环境变量，std::env::vars() 遇到它们会 panic
+            vars: vars(
+                &config.env,
+                std::env::vars_os().filter_map(|(name, value)| {
+                    Some((name.into_string().ok()?, value.into_string().ok()?))
+                }),
+            ),
+        }))
+    }
+
+    // 替换 {{ VAR }} 占位符，内容不是变量名的 {{ }} 保持原样，\{{ 输出字面的 {{
+    pub(crate) fn render(&self, path: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+        if !glob_matches_any(&self.include, path) {
+            return Ok(data);
+        }
+        let mut output = Vec::with_capacity(data.len());
+        let mut rest = data.as_slice();
+        while let Some(start) = find_bytes(rest, b"{{") {
+            if start > 0 && rest[start - 1] == b'\\' {
+                output.extend(&rest[..start - 1]);
+                output.extend(b"{{");
+                rest = &rest[start + 2..];
+                continue;
+            }
+            let end = match find_bytes(&rest[start..], b"}}") {
+                Some(end) => start + end,
+                None => break,
+            };
+            output.extend(&rest[..start]);
+            let name = String::from_utf8_lossy(&rest[start + 2..end]);
+            let name = name.trim();
+            let is_name =
+                !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            match self.vars.get(name) {
+                Some(value) if is_name => output.extend(value.as_bytes()),
+                None if is_name => {
+                    let message = format!(
+                        "undefined template variable {} in {} (write \\{{{{ for a literal {{{{)",
+                        name, path
+                    );
+                    return Err(io::Error::new(io::ErrorKind::NotFound, message));
+                }
+                _ => output.extend(&rest[start..end + 2]),
+            }
+            rest = &rest[end + 2..];
+        }
+        output.extend(rest);
+        Ok(output)
+    }
+}
+
+// 变量来自 [env] 与去掉前缀的 NEUTAURI_PUBLIC_* 环境变量，同名时环境变量优先
+fn vars<I>(env: &BTreeMap<String, String>, process_env: I) -> BTreeMap<String, String>
+where
+    I: Iterator<Item = (String, String)>,
+{
+    let mut vars = env.clone();
+    vars.extend(process_env.filter_map(|(name, value)| {
+        let name = name.strip_prefix(ENV_PREFIX)?;
+        Some((name.to_string(), value))
+    }));
+    vars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renderer() -> Renderer {
+        let env = [("VERSION", "1.0"), ("API_URL", "https://a.example")]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<BTreeMap<_, _>>();
+        let process_env = [
+            ("NEUTAURI_PUBLIC_API_URL", "https://b.example"),
+            ("NEUTAURI_PUBLIC_BUILD", "42"),
+            ("HOME", "/root"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        Renderer {
+            include: glob_patterns(&["index.html".into()]).unwrap(),
+            vars: vars(&env, process_env),
+        }
+    }
+
+    fn render(data: &str) -> Result<String> {
+        let output = renderer().render("/index.html", data.as_bytes().to_vec())?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn variables() {
+        // 环境变量去掉前缀后覆盖 [env] 中的同名变量
+        assert_eq!(
+            render("v{{VERSION}} {{ API_URL }} #{{ BUILD }}").unwrap(),
+            "v1.0 https://b.example #42"
+        );
+        assert!(render("{{ HOME }}").is_err());
+        assert!(render("{{ NEUTAURI_PUBLIC_BUILD }}").is_err());
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(
+            render("{{ a + b }} {{ }} {{ x").unwrap(),
+            "{{ a + b }} {{ }} {{ x"
+        );
+        assert_eq!(
+            render(r"\{{ message }} {{ VERSION }}").unwrap(),
+            "{{ message }} 1.0"
+        );
+        let error = render("{{ message }}").unwrap_err();
+        assert!(error.to_string().contains("message"));
+        // 不匹配 include 的文件原样返回
+        let output = renderer()
+            .render("/app.js", b"{{ message }}".to_vec())
+            .unwrap();
+        assert_eq!(output, b"{{ message }}");
+    }
+}
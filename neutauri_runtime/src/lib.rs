@@ -0,0 +1,39 @@
+use std::io;
+
+pub use neutauri_core::{App, Asset, AssetSource, Commands, Hooks, Layered, LocalDir};
+pub use neutauri_data as data;
+pub use wry;
+
+// 读取内嵌在当前程序中的数据，没有时读取工作目录下的 data.neu，
+// 数据损坏或格式版本不匹配时直接返回错误
+pub fn load() -> io::Result<data::Data> {
+    let exe = std::env::current_exe()?;
+    match data::load(exe.as_path()) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            data::load("data.neu").map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no bundle data in {} and no data.neu found", exe.display()),
+                ),
+                _ => e,
+            })
+        }
+        result => result,
+    }
+}
+
+// 与预编译的 neutauri_runtime 相同的启动流程，数据目录以当前程序的文件名命名
+pub fn run(config: data::Data, hooks: Hooks) -> wry::Result<()> {
+    let path = std::env::current_exe()?;
+    let identifier = path
+        .file_stem()
+        .unwrap_or_else(|| "neutauri_app".as_ref())
+        .to_string_lossy()
+        .to_string();
+    let window_attr = config.window_attr.clone();
+    let webview_attr = config.webview_attr.clone();
+    App::new(window_attr, webview_attr, config)
+        .with_identifier(identifier)
+        .with_hooks(hooks)
+        .run()
+}